@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use parking_lot::Mutex;
+
+use crate::{CacheSize, HashedKey, object::ObjectSize, sketch::FrequencySketch};
+
+/// Decides whether a new object is admitted to the cache. The admission
+/// policy is consulted by `set` before a key which is not already in the
+/// cache is inserted.
+///
+/// # Examples
+/// ```
+/// use paper_cache::{Admission, PaperCache, PaperPolicy};
+///
+/// struct SmallObjects;
+///
+/// impl Admission for SmallObjects {
+///     fn admit(&self, _key: u64, size: u32, _available_size: u64, _last_evicted: Option<u64>) -> bool {
+///         size <= 100
+///     }
+/// }
+///
+/// let cache = PaperCache::<u32, u32>::builder(
+///     1000,
+///     &[PaperPolicy::Lfu],
+///     PaperPolicy::Lfu,
+/// )
+/// .admission(SmallObjects)
+/// .build();
+///
+/// assert!(cache.is_ok());
+/// ```
+pub trait Admission: Send + Sync {
+	/// Records an access of the supplied hashed key. Invoked on every get.
+	fn record(&self, _key: HashedKey) {}
+
+	/// Returns `true` if an object with the supplied hashed key and size
+	/// should be admitted. The available size is the space remaining in
+	/// the cache before any objects are evicted.
+	///
+	/// The last evicted key is the hashed key of the object most recently
+	/// evicted by the cache's policy, or `None` if the cache has not yet
+	/// evicted any objects. The object has already left the cache and the
+	/// key only changes once another object is evicted, so it is a heuristic
+	/// stand-in for the object which admitting a new one would evict rather
+	/// than the policy's current eviction candidate.
+	fn admit(
+		&self,
+		key: HashedKey,
+		size: ObjectSize,
+		available_size: CacheSize,
+		last_evicted: Option<HashedKey>,
+	) -> bool;
+}
+
+/// A TinyLFU admission policy, which estimates the recent access frequency
+/// of each key with a [count-min sketch](https://en.wikipedia.org/wiki/Count%E2%80%93min_sketch).
+///
+/// While the cache has space available, every object is admitted. Once the
+/// cache is full, a new object is only admitted if its key was accessed more
+/// frequently than the most recently evicted key, so that keys which are
+/// accessed once (e.g., by a scan) do not evict the cache's hot objects.
+/// The most recently evicted key approximates the object which admitting a
+/// new one would evict, since the policy's next victim is not known when a
+/// key is set.
+///
+/// Accesses are recorded on a best-effort basis: an access which is recorded
+/// while another thread holds the sketch is dropped rather than blocking the
+/// get, so that concurrent gets are not serialized by the sketch.
+pub struct TinyLfu {
+	sketch: Mutex<FrequencySketch>,
+}
+
+impl TinyLfu {
+	/// Creates a TinyLFU admission policy which tracks the frequencies of
+	/// approximately `capacity` distinct keys.
+	#[must_use]
+	pub fn new(capacity: usize) -> Self {
+		TinyLfu {
+			sketch: Mutex::new(FrequencySketch::new(capacity)),
+		}
+	}
+}
+
+impl Admission for TinyLfu {
+	fn record(&self, key: HashedKey) {
+		if let Some(mut sketch) = self.sketch.try_lock() {
+			sketch.increment(key);
+		}
+	}
+
+	fn admit(
+		&self,
+		key: HashedKey,
+		size: ObjectSize,
+		available_size: CacheSize,
+		last_evicted: Option<HashedKey>,
+	) -> bool {
+		if size as CacheSize <= available_size {
+			return true;
+		}
+
+		let mut sketch = self.sketch.lock();
+
+		// until an object has been evicted, the last evicted key is assumed to
+		// have never been accessed
+		let last_evicted_frequency = last_evicted.map_or(0, |evicted| sketch.frequency(evicted));
+
+		if sketch.frequency(key) > last_evicted_frequency {
+			return true;
+		}
+
+		// a rejected set counts as an access so that a key which is set
+		// repeatedly is eventually admitted
+		sketch.increment(key);
+		false
+	}
+}
+
+/// An admission policy which rejects objects larger than a maximum size.
+///
+/// # Examples
+/// ```
+/// use paper_cache::{PaperCache, PaperPolicy, SizeThreshold};
+///
+/// let cache = PaperCache::<u32, u32>::builder(
+///     1000,
+///     &[PaperPolicy::Lfu],
+///     PaperPolicy::Lfu,
+/// )
+/// .admission(SizeThreshold::new(100))
+/// .build();
+///
+/// assert!(cache.is_ok());
+/// ```
+pub struct SizeThreshold {
+	max_object_size: ObjectSize,
+}
+
+impl SizeThreshold {
+	/// Creates an admission policy which rejects objects whose size exceeds
+	/// `max_object_size` bytes.
+	#[must_use]
+	pub fn new(max_object_size: ObjectSize) -> Self {
+		SizeThreshold {
+			max_object_size,
+		}
+	}
+}
+
+impl Admission for SizeThreshold {
+	fn admit(
+		&self,
+		_key: HashedKey,
+		size: ObjectSize,
+		_available_size: CacheSize,
+		_last_evicted: Option<HashedKey>,
+	) -> bool {
+		size <= self.max_object_size
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::admission::{Admission, TinyLfu};
+
+	#[test]
+	fn tiny_lfu_compares_against_the_last_evicted_key() {
+		let admission = TinyLfu::new(1024);
+
+		// objects are always admitted while the cache has space available
+		assert!(admission.admit(0, 10, 100, None));
+
+		for _ in 0..3 {
+			admission.record(1);
+		}
+
+		for _ in 0..5 {
+			admission.record(2);
+		}
+
+		assert!(!admission.admit(0, 10, 0, Some(1)));
+		assert!(admission.admit(2, 10, 0, Some(1)));
+		assert!(!admission.admit(1, 10, 0, Some(2)));
+	}
+}
@@ -0,0 +1,532 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	hash::{BuildHasher, Hash, RandomState},
+	sync::Arc,
+};
+
+use log::error;
+use parking_lot::Mutex;
+use tokio::{sync::oneshot, task};
+use typesize::TypeSize;
+
+use crate::{
+	AutoPolicyConfig,
+	AutoPolicyDecision,
+	CacheError,
+	CacheSize,
+	PaperCache,
+	PaperPolicy,
+	PolicyReport,
+	SetOptions,
+	loader::AsyncLoaders,
+	object::ObjectSize,
+	status::Status,
+	worker::WorkerCallback,
+};
+
+/// An asynchronous facade over [`PaperCache`].
+///
+/// Operations which may block (i.e., those which wait for room in the
+/// workers' event queue with [`Backpressure::Block`](crate::Backpressure),
+/// for evictions in strict mode, or for a response from the workers) are run
+/// on tokio's blocking thread pool, so they never block the executor.
+pub struct AsyncPaperCache<K, V, S = RandomState> {
+	cache:   Arc<PaperCache<K, V, S>>,
+	loaders: AsyncLoaders,
+}
+
+impl<K, V, S> AsyncPaperCache<K, V, S>
+where
+	K: 'static + Eq + Hash + TypeSize + Send + Sync,
+	V: 'static + TypeSize + Send + Sync,
+	S: 'static + Default + Clone + BuildHasher + Send + Sync,
+{
+	/// Creates an empty `AsyncPaperCache` with maximum size `max_size` and
+	/// eviction policy `policy`. See [`PaperCache::new`] for the configuration
+	/// errors which may be returned.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use paper_cache::{AsyncPaperCache, PaperPolicy};
+	///
+	/// let cache = AsyncPaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// );
+	///
+	/// assert!(cache.is_ok());
+	/// ```
+	pub fn new(
+		max_size: CacheSize,
+		policies: &[PaperPolicy],
+		policy: PaperPolicy,
+	) -> Result<Self, CacheError> {
+		let cache = PaperCache::new(max_size, policies, policy)?;
+		Ok(AsyncPaperCache::from(cache))
+	}
+
+	/// Returns the underlying [`PaperCache`].
+	#[must_use]
+	pub fn cache(&self) -> &PaperCache<K, V, S> {
+		&self.cache
+	}
+
+	/// Returns the current statistics.
+	pub async fn status(&self) -> Result<Status, CacheError> {
+		self.cache.status()
+	}
+
+	/// Returns the most recent decisions of the auto policy.
+	/// See [`PaperCache::auto_policy_history`].
+	pub async fn auto_policy_history(&self) -> Vec<AutoPolicyDecision> {
+		self.cache.auto_policy_history()
+	}
+
+	/// Returns the estimated performance of each of the configured policies.
+	/// See [`PaperCache::policy_report`].
+	pub async fn policy_report(&self) -> Result<PolicyReport, CacheError> {
+		self.run_blocking(|cache| cache.policy_report()).await
+	}
+
+	/// Gets the value associated with the supplied key.
+	/// If the key was not found in the cache, returns a [`CacheError`].
+	pub async fn get(&self, key: &K) -> Result<Arc<V>, CacheError> {
+		self.cache.get(key)
+	}
+
+	/// Gets the values associated with each of the supplied keys.
+	/// See [`PaperCache::mget`].
+	pub async fn mget(&self, keys: &[K]) -> Result<Vec<Result<Arc<V>, CacheError>>, CacheError> {
+		self.cache.mget(keys)
+	}
+
+	/// Sets the supplied key and value in the cache.
+	/// See [`PaperCache::set`].
+	pub async fn set(&self, key: K, value: V, ttl: Option<u32>) -> Result<(), CacheError> {
+		self.run_blocking(move |cache| cache.set(key, value, ttl))
+			.await
+	}
+
+	/// Sets the supplied key and value in the cache with the supplied options.
+	/// See [`PaperCache::set_with_options`].
+	pub async fn set_with_options(&self, key: K, value: V, options: SetOptions) -> Result<(), CacheError> {
+		self.run_blocking(move |cache| cache.set_with_options(key, value, options))
+			.await
+	}
+
+	/// Sets each of the supplied keys and values in the cache.
+	/// See [`PaperCache::mset`].
+	pub async fn mset(
+		&self,
+		objects: impl IntoIterator<Item = (K, V, Option<u32>)>,
+	) -> Result<Vec<Result<(), CacheError>>, CacheError> {
+		let objects = objects.into_iter().collect::<Vec<_>>();
+
+		self.run_blocking(move |cache| cache.mset(objects))
+			.await
+	}
+
+	/// Deletes the object associated with the supplied key in the cache.
+	/// Returns a [`CacheError`] if the key was not found in the cache.
+	pub async fn del(&self, key: &K) -> Result<(), CacheError>
+	where
+		K: Clone,
+	{
+		let key = key.clone();
+
+		self.run_blocking(move |cache| cache.del(&key))
+			.await
+	}
+
+	/// Deletes the objects associated with each of the supplied keys.
+	/// See [`PaperCache::mdel`].
+	pub async fn mdel(&self, keys: &[K]) -> Result<Vec<Result<(), CacheError>>, CacheError>
+	where
+		K: Clone,
+	{
+		let keys = keys.to_vec();
+
+		self.run_blocking(move |cache| cache.mdel(&keys))
+			.await
+	}
+
+	/// Checks if an object with the supplied key exists in the cache without
+	/// altering any of the cache's internal queues.
+	pub async fn has(&self, key: &K) -> bool {
+		self.cache.has(key)
+	}
+
+	/// Gets (peeks) the value associated with the supplied key without altering
+	/// any of the cache's internal queues.
+	pub async fn peek(&self, key: &K) -> Result<Arc<V>, CacheError> {
+		self.cache.peek(key)
+	}
+
+	/// Sets the TTL associated with the supplied key.
+	/// If the key was not found in the cache, returns a [`CacheError`].
+	pub async fn ttl(&self, key: &K, ttl: Option<u32>) -> Result<(), CacheError>
+	where
+		K: Clone,
+	{
+		let key = key.clone();
+
+		self.run_blocking(move |cache| cache.ttl(&key, ttl))
+			.await
+	}
+
+	/// Gets the size of the value associated with the supplied key in bytes.
+	/// If the key was not found in the cache, returns a [`CacheError`].
+	pub async fn size(&self, key: &K) -> Result<ObjectSize, CacheError> {
+		self.cache.size(key)
+	}
+
+	/// Deletes all objects in the cache and sets the cache's used size to zero.
+	pub async fn wipe(&self) -> Result<(), CacheError> {
+		self.run_blocking(|cache| cache.wipe()).await
+	}
+
+	/// Resizes the cache to the supplied maximum size and waits until any
+	/// resulting evictions have been applied.
+	/// If the supplied size is zero, returns a [`CacheError`].
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{AsyncPaperCache, PaperPolicy};
+	///
+	/// # tokio_test();
+	/// # #[tokio::main(flavor = "current_thread")]
+	/// # async fn tokio_test() {
+	/// let cache = AsyncPaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// assert!(cache.resize(1).await.is_ok());
+	///
+	/// // Resizing to a size of zero will return a CacheError.
+	/// assert!(cache.resize(0).await.is_err());
+	/// # }
+	/// ```
+	pub async fn resize(&self, max_size: CacheSize) -> Result<(), CacheError> {
+		self.run_blocking(move |cache| cache.resize(max_size))
+			.await?;
+
+		self.settle().await
+	}
+
+	/// Changes how the auto policy selects one of the configured policies.
+	/// See [`PaperCache::configure_auto_policy`].
+	pub async fn configure_auto_policy(&self, config: AutoPolicyConfig) -> Result<(), CacheError> {
+		self.run_blocking(move |cache| cache.configure_auto_policy(config))
+			.await
+	}
+
+	/// Sets the eviction policy of the cache to the supplied policy and waits
+	/// until the policy switch has been completed.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{AsyncPaperCache, PaperPolicy};
+	///
+	/// # tokio_test();
+	/// # #[tokio::main(flavor = "current_thread")]
+	/// # async fn tokio_test() {
+	/// let cache = AsyncPaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu, PaperPolicy::Lru],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// assert!(cache.policy(PaperPolicy::Lru).await.is_ok());
+	/// assert!(cache.policy(PaperPolicy::Fifo).await.is_err());
+	/// # }
+	/// ```
+	pub async fn policy(&self, policy: PaperPolicy) -> Result<(), CacheError> {
+		self.run_blocking(move |cache| cache.policy(policy))
+			.await?;
+
+		self.settle().await
+	}
+
+	/// Gets the value associated with the supplied key or, if the key was not
+	/// found in the cache, sets the value resolved by the supplied loader with
+	/// the supplied TTL and returns it.
+	///
+	/// When multiple callers miss the same key concurrently, the loader of only
+	/// one of them is awaited while the others wait for its value.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{AsyncPaperCache, PaperPolicy};
+	///
+	/// # tokio_test();
+	/// # #[tokio::main(flavor = "current_thread")]
+	/// # async fn tokio_test() {
+	/// let cache = AsyncPaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// let value = cache.get_or_insert_with(0, None, || async { 1 }).await;
+	/// assert_eq!(value.as_deref(), Ok(&1));
+	/// # }
+	/// ```
+	pub async fn get_or_insert_with<F, Fut>(
+		&self,
+		key: K,
+		ttl: Option<u32>,
+		loader: F,
+	) -> Result<Arc<V>, CacheError>
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = V>,
+	{
+		self.try_get_or_insert_with(key, ttl, move || async move { Ok(loader().await) })
+			.await
+	}
+
+	/// Gets the value associated with the supplied key or, if the key was not
+	/// found in the cache, sets the value resolved by the supplied fallible
+	/// loader with the supplied TTL and returns it. If the loader fails, its
+	/// error is returned and nothing is set in the cache.
+	///
+	/// When multiple callers miss the same key concurrently, the loader of only
+	/// one of them is awaited while the others wait for its value. If that
+	/// loader fails or is cancelled, one of the waiting callers awaits its own
+	/// loader instead.
+	pub async fn try_get_or_insert_with<F, Fut, E>(
+		&self,
+		key: K,
+		ttl: Option<u32>,
+		loader: F,
+	) -> Result<Arc<V>, E>
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<V, E>>,
+		E: From<CacheError>,
+	{
+		match self.cache.get(&key) {
+			Ok(value) => return Ok(value),
+			Err(CacheError::KeyNotFound) => {},
+			Err(err) => return Err(err.into()),
+		}
+
+		let hashed_key = self.cache.hash_key(&key);
+
+		let _guard = loop {
+			if let Some(guard) = self.loaders.join(hashed_key).await {
+				break guard;
+			}
+
+			// another caller finished loading, so its value should now be in
+			// the cache unless the load failed (or the value was evicted)
+			if let Ok(value) = self.cache.peek(&key) {
+				return Ok(value);
+			}
+		};
+
+		// another caller may have finished loading between the miss and
+		// this caller becoming responsible for the load
+		if let Ok(value) = self.cache.peek(&key) {
+			return Ok(value);
+		}
+
+		let value = loader().await?;
+
+		let value = self
+			.run_blocking(move |cache| cache.set_loaded(key, value, ttl))
+			.await?;
+
+		Ok(value)
+	}
+
+	async fn settle(&self) -> Result<(), CacheError> {
+		let (sender, receiver) = oneshot::channel();
+		let sender = Mutex::new(Some(sender));
+
+		let callback: WorkerCallback = Arc::new(move || {
+			if let Some(sender) = sender.lock().take() {
+				sender.send(()).ok();
+			}
+		});
+
+		self.run_blocking(move |cache| cache.settle_with(callback))
+			.await?;
+
+		// if the workers shut down before invoking the callback, the callback
+		// (and so the sender) is dropped, which resolves the receiver with an
+		// error rather than waiting forever
+		receiver.await.map_err(|_| {
+			error!("Could not receive settle confirmation from the workers");
+			CacheError::Internal
+		})
+	}
+
+	/// Runs the supplied operation on tokio's blocking thread pool.
+	async fn run_blocking<T, F>(&self, operation: F) -> Result<T, CacheError>
+	where
+		T: 'static + Send,
+		F: 'static + FnOnce(&PaperCache<K, V, S>) -> Result<T, CacheError> + Send,
+	{
+		let cache = self.cache.clone();
+
+		task::spawn_blocking(move || operation(&cache))
+			.await
+			.map_err(|err| {
+				error!("Could not run blocking cache operation: {err:?}");
+				CacheError::Internal
+			})?
+	}
+}
+
+impl<K, V, S> From<PaperCache<K, V, S>> for AsyncPaperCache<K, V, S> {
+	fn from(cache: PaperCache<K, V, S>) -> Self {
+		AsyncPaperCache {
+			cache: Arc::new(cache),
+			loaders: AsyncLoaders::default(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{AsyncPaperCache, CacheError, PaperPolicy};
+
+	#[tokio::test]
+	async fn it_gets_or_inserts_a_non_existing_object() {
+		let cache = init_test_cache();
+
+		let value = cache
+			.get_or_insert_with(0, None, || async { 1 })
+			.await;
+
+		assert_eq!(value.as_deref(), Ok(&1));
+		assert_eq!(cache.get(&0).await.as_deref(), Ok(&1));
+	}
+
+	#[tokio::test]
+	async fn it_does_not_insert_a_failed_load() {
+		let cache = init_test_cache();
+
+		let result = cache
+			.try_get_or_insert_with(0, None, || async { Err(CacheError::Internal) })
+			.await;
+
+		assert_eq!(result, Err(CacheError::Internal));
+		assert!(!cache.has(&0).await);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn it_loads_a_concurrently_missed_object_once() {
+		use std::{
+			sync::{
+				Arc,
+				atomic::{AtomicU32, Ordering},
+			},
+			time::Duration,
+		};
+
+		let cache = Arc::new(init_test_cache());
+		let num_loads = Arc::new(AtomicU32::new(0));
+
+		let handles = (0..8)
+			.map(|_| {
+				let cache = cache.clone();
+				let num_loads = num_loads.clone();
+
+				tokio::spawn(async move {
+					cache
+						.get_or_insert_with(0, None, || async {
+							tokio::time::sleep(Duration::from_millis(100)).await;
+							num_loads.fetch_add(1, Ordering::Relaxed) + 1
+						})
+						.await
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for handle in handles {
+			assert_eq!(handle.await.unwrap().as_deref(), Ok(&1));
+		}
+
+		assert_eq!(num_loads.load(Ordering::Relaxed), 1);
+	}
+
+	#[tokio::test]
+	async fn it_awaits_a_policy_switch() {
+		let cache = AsyncPaperCache::<u32, u32>::new(
+			1000,
+			&[PaperPolicy::Lfu, PaperPolicy::Lru],
+			PaperPolicy::Lfu,
+		)
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, None).await.is_ok());
+		assert!(cache.policy(PaperPolicy::Lru).await.is_ok());
+
+		let status = cache.status().await.unwrap();
+		assert_eq!(status.policy(), PaperPolicy::Lru);
+	}
+
+	#[tokio::test(flavor = "current_thread")]
+	async fn it_does_not_block_the_executor_when_the_queue_is_full() {
+		use std::time::Duration;
+
+		use crossbeam_channel::unbounded;
+
+		use crate::{Backpressure, PaperCache};
+
+		let (entered_tx, entered_rx) = unbounded::<()>();
+		let (release_tx, release_rx) = unbounded::<()>();
+
+		// the policy worker blocks in the removal listener until the release
+		// sender is dropped, so the sets wait for room in the queue
+		let cache = PaperCache::<u32, u32>::builder(1000, &[PaperPolicy::Lfu], PaperPolicy::Lfu)
+			.channel_capacity(1)
+			.backpressure(Backpressure::Block)
+			.listener(move |_, _, _| {
+				entered_tx.send(()).ok();
+				release_rx.recv().ok();
+			})
+			.build()
+			.map(AsyncPaperCache::from)
+			.expect("Could not initialize test cache");
+
+		for key in 0..5 {
+			assert!(cache.set(key, 1, None).await.is_ok());
+		}
+
+		// the evictions of the resize are applied by the policy worker
+		assert!(cache.cache().resize(100).is_ok());
+		assert!(entered_rx.recv().is_ok());
+
+		let sets = tokio::spawn(async move {
+			for key in 100..110 {
+				assert!(cache.set(key, 1, None).await.is_ok());
+			}
+		});
+
+		// the single-threaded executor can only wake this task if the
+		// blocked sets are not running on it
+		tokio::time::sleep(Duration::from_millis(100)).await;
+		assert!(!sets.is_finished());
+
+		drop(release_tx);
+		assert!(sets.await.is_ok());
+	}
+
+	fn init_test_cache() -> AsyncPaperCache<u32, u32> {
+		AsyncPaperCache::<u32, u32>::new(1000, &[PaperPolicy::Lfu], PaperPolicy::Lfu)
+			.expect("Could not initialize test cache")
+	}
+}
@@ -0,0 +1,483 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	hash::{BuildHasher, Hash, RandomState},
+	path::Path,
+	sync::{Arc, atomic::AtomicBool},
+};
+
+use crossbeam_channel::bounded;
+use dashmap::DashMap;
+use kwik::math::set::Multiset;
+use parking_lot::Mutex;
+use serde::{Serialize, de::DeserializeOwned};
+use typesize::TypeSize;
+
+use crate::{
+	Admission,
+	AdmissionRef,
+	AutoPolicyConfig,
+	CacheError,
+	CacheSize,
+	FsyncPolicy,
+	NoHasher,
+	PaperCache,
+	PaperPolicy,
+	PolicyStack,
+	RemovalCause,
+	RemovalListenerRef,
+	loader::Loaders,
+	object::{ObjectSize, overhead::OverheadManager},
+	op_log::{FileOpLog, OpLog},
+	policy::{CustomPolicies, PolicyStackFactory},
+	status::AtomicStatus,
+	worker::{Backpressure, WorkerManager, register_worker},
+};
+
+const DEFAULT_CHANNEL_CAPACITY: usize = 65_536;
+
+type OpLogOpener<K, V, S> =
+	Box<dyn FnOnce(&PaperCache<K, V, S>) -> Result<Arc<dyn OpLog<K, V>>, CacheError>>;
+
+/// Configures and creates a [`PaperCache`].
+pub struct PaperCacheBuilder<K, V, S = RandomState> {
+	max_size:    CacheSize,
+	policies:    Box<[PaperPolicy]>,
+	policy:      PaperPolicy,
+	auto_policy: AutoPolicyConfig,
+
+	channel_capacity: usize,
+	backpressure:     Backpressure,
+	strict:           bool,
+
+	removal_listener: RemovalListenerRef<K, V>,
+	op_log:           Option<OpLogOpener<K, V, S>>,
+	admission:        AdmissionRef,
+	custom_policies:  Vec<(&'static str, ObjectSize, PolicyStackFactory)>,
+	hasher:           S,
+}
+
+impl<K, V, S> PaperCacheBuilder<K, V, S>
+where
+	K: 'static + Eq + Hash + TypeSize,
+	V: 'static + TypeSize,
+	S: Default + Clone + BuildHasher,
+{
+	/// Creates a builder for a `PaperCache` with maximum size `max_size`
+	/// and eviction policy `policy`.
+	pub fn new(max_size: CacheSize, policies: &[PaperPolicy], policy: PaperPolicy) -> Self {
+		PaperCacheBuilder {
+			max_size,
+			policies: policies.into(),
+			policy,
+			auto_policy: AutoPolicyConfig::default(),
+
+			channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+			backpressure: Backpressure::default(),
+			strict: false,
+
+			removal_listener: None,
+			op_log: None,
+			admission: None,
+			custom_policies: Vec::new(),
+			hasher: S::default(),
+		}
+	}
+
+	/// Sets the hasher used to hash the cache's keys.
+	#[must_use]
+	pub fn hasher(mut self, hasher: S) -> Self {
+		self.hasher = hasher;
+		self
+	}
+
+	/// Configures how the [`PaperPolicy::Auto`] policy selects one of the
+	/// configured policies. The configuration can also be changed while the
+	/// cache is running with [`PaperCache::configure_auto_policy`].
+	///
+	/// # Examples
+	/// ```
+	/// use std::time::Duration;
+	///
+	/// use paper_cache::{AutoPolicyConfig, AutoPolicyObjective, PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lfu, PaperPolicy::Gdsf],
+	///     PaperPolicy::Auto,
+	/// )
+	/// .auto_policy(
+	///     AutoPolicyConfig::new()
+	///         .epoch(Duration::from_secs(300))
+	///         .objective(AutoPolicyObjective::MissCost),
+	/// )
+	/// .build();
+	///
+	/// assert!(cache.is_ok());
+	/// ```
+	#[must_use]
+	pub fn auto_policy(mut self, config: AutoPolicyConfig) -> Self {
+		self.auto_policy = config;
+		self
+	}
+
+	/// Sets the maximum number of pending events in each of the workers'
+	/// event queues. Defaults to 65,536.
+	///
+	/// When a queue is full, gets are not recorded by the eviction policy
+	/// (and are counted in the status' `dropped_gets`), while all other
+	/// operations are handled according to the configured [`Backpressure`].
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{Backpressure, PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// )
+	/// .channel_capacity(1024)
+	/// .backpressure(Backpressure::FailFast)
+	/// .build();
+	///
+	/// assert!(cache.is_ok());
+	/// ```
+	#[must_use]
+	pub fn channel_capacity(mut self, capacity: usize) -> Self {
+		self.channel_capacity = capacity;
+		self
+	}
+
+	/// Sets how operations other than gets behave when the workers' event
+	/// queue is full. Defaults to [`Backpressure::Block`].
+	///
+	/// When blocking, a removal listener must not modify the cache, since the
+	/// queue cannot drain while the listener is running on a worker thread.
+	/// Failing fast is best-effort, so the same applies to a cache which
+	/// fails fast under contention.
+	#[must_use]
+	pub fn backpressure(mut self, backpressure: Backpressure) -> Self {
+		self.backpressure = backpressure;
+		self
+	}
+
+	/// Enables strict mode, in which the cache's used size never exceeds its
+	/// maximum size. Rather than evicting objects in the background, `set`
+	/// waits until enough objects have been evicted for the new object to
+	/// fit, or returns [`CacheError::CacheFull`] if it cannot be made to fit.
+	///
+	/// Operations which grow the cache are serialized in strict mode, and a
+	/// removal listener must not modify the cache since evictions are applied
+	/// while those operations wait.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// )
+	/// .strict()
+	/// .build()
+	/// .unwrap();
+	///
+	/// for key in 0..100 {
+	///     cache.set(key, key, None).unwrap();
+	///     assert!(cache.status().unwrap().used_size() <= 1000);
+	/// }
+	/// ```
+	#[must_use]
+	pub fn strict(mut self) -> Self {
+		self.strict = true;
+		self
+	}
+
+	/// Registers a listener which is invoked with the original key, the value,
+	/// and the [`RemovalCause`] whenever an object leaves the cache.
+	///
+	/// The listener is invoked on the thread which removed the object, which
+	/// is one of the cache's worker threads for evictions and expirations.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy, RemovalCause};
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// )
+	/// .listener(|key, value, cause| {
+	///     if cause == RemovalCause::Evicted {
+	///         println!("{key} was evicted with value {value}");
+	///     }
+	/// })
+	/// .build();
+	///
+	/// assert!(cache.is_ok());
+	/// ```
+	#[must_use]
+	pub fn listener<F>(mut self, listener: F) -> Self
+	where
+		F: 'static + Fn(K, Arc<V>, RemovalCause) + Send + Sync,
+	{
+		self.removal_listener = Some(Arc::new(listener));
+		self
+	}
+
+	/// Sets the [`Admission`] policy which decides whether a new object is
+	/// admitted to the cache. By default, every object is admitted.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy, TinyLfu};
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// )
+	/// .admission(TinyLfu::new(1024))
+	/// .build();
+	///
+	/// assert!(cache.is_ok());
+	/// ```
+	#[must_use]
+	pub fn admission<A>(mut self, admission: A) -> Self
+	where
+		A: 'static + Admission,
+	{
+		self.admission = Some(Arc::new(admission));
+		self
+	}
+
+	/// Registers a custom eviction policy named `name`, which is configured
+	/// with [`PaperPolicy::Custom`]. The factory creates a [`PolicyStack`] for
+	/// the supplied maximum cache size, and `overhead` is the policy's
+	/// estimated number of bytes per object.
+	///
+	/// Like the built-in policies, a custom policy may be selected when the
+	/// cache's policy is [`PaperPolicy::Auto`]. Custom policies belong to the
+	/// cache which registers them, so their names can only be parsed with
+	/// [`PaperCache::parse_policy`].
+	///
+	/// # Examples
+	/// ```
+	/// use std::time::Instant;
+	///
+	/// use paper_cache::{PaperCache, PaperPolicy, PolicyStack};
+	///
+	/// // evicts the most recently inserted key
+	/// #[derive(Default)]
+	/// struct Newest {
+	///     keys: Vec<u64>,
+	/// }
+	///
+	/// impl PolicyStack for Newest {
+	///     fn is_policy(&self, policy: &PaperPolicy) -> bool {
+	///         *policy == PaperPolicy::Custom("newest")
+	///     }
+	///
+	///     fn len(&self) -> usize {
+	///         self.keys.len()
+	///     }
+	///
+	///     fn contains(&self, key: u64) -> bool {
+	///         self.keys.contains(&key)
+	///     }
+	///
+	///     fn insert(&mut self, key: u64, _size: u32, _expiry: Option<Instant>) {
+	///         if !self.contains(key) {
+	///             self.keys.push(key);
+	///         }
+	///     }
+	///
+	///     fn remove(&mut self, key: u64) {
+	///         self.keys.retain(|got_key| *got_key != key);
+	///     }
+	///
+	///     fn clear(&mut self) {
+	///         self.keys.clear();
+	///     }
+	///
+	///     fn evict_one(&mut self) -> Option<u64> {
+	///         self.keys.pop()
+	///     }
+	/// }
+	///
+	/// let policy = PaperPolicy::Custom("newest");
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lru, policy],
+	///     policy,
+	/// )
+	/// .custom_policy("newest", 8, |_| Box::new(Newest::default()))
+	/// .build();
+	///
+	/// assert!(cache.is_ok());
+	/// ```
+	#[must_use]
+	pub fn custom_policy<F>(mut self, name: &'static str, overhead: ObjectSize, factory: F) -> Self
+	where
+		F: 'static + Fn(CacheSize) -> Box<dyn PolicyStack> + Send + Sync,
+	{
+		self.custom_policies
+			.push((name, overhead, Arc::new(factory)));
+
+		self
+	}
+
+	/// Creates the configured `PaperCache`. See [`PaperCache::new`] for the
+	/// configuration errors which may be returned.
+	pub fn build(self) -> Result<PaperCache<K, V, S>, CacheError> {
+		let max_size = self.max_size;
+		let policies = &*self.policies;
+		let policy = self.policy;
+
+		if max_size == 0 {
+			return Err(CacheError::ZeroCacheSize);
+		}
+
+		if policies.is_empty() {
+			return Err(CacheError::EmptyPolicies);
+		}
+
+		if policies.contains(&PaperPolicy::Auto) {
+			return Err(CacheError::ConfiguredAutoPolicy);
+		}
+
+		if policies.iter().is_multiset() {
+			return Err(CacheError::DuplicatePolicies);
+		}
+
+		if !policy.is_auto() && !policies.contains(&policy) {
+			return Err(CacheError::UnconfiguredPolicy);
+		}
+
+		if self.channel_capacity == 0 {
+			return Err(CacheError::ZeroChannelCapacity);
+		}
+
+		let mut custom_policies = CustomPolicies::default();
+
+		for (name, overhead, factory) in self.custom_policies {
+			custom_policies.register(name, overhead, factory)?;
+		}
+
+		if policies.iter().any(|policy| {
+			matches!(policy, PaperPolicy::Custom(name) if !custom_policies.contains(name))
+		}) {
+			return Err(CacheError::UnregisteredPolicy);
+		}
+
+		let custom_policies = Arc::new(custom_policies);
+
+		let objects = Arc::new(DashMap::with_hasher(NoHasher::default()));
+		let status = Arc::new(AtomicStatus::new(
+			max_size,
+			policies,
+			policy,
+			custom_policies.clone(),
+		)?);
+		let overhead_manager = Arc::new(OverheadManager::new(&status));
+
+		let (worker_sender, worker_listener) = bounded(self.channel_capacity);
+
+		let worker_manager = WorkerManager::new(
+			worker_listener,
+			&objects,
+			&status,
+			&overhead_manager,
+			&self.removal_listener,
+			self.channel_capacity,
+			self.auto_policy,
+		)?;
+
+		let worker_handle = register_worker(worker_manager);
+
+		let mut cache = PaperCache {
+			objects,
+			status,
+
+			worker_manager: Arc::new(worker_sender),
+			worker_handle: Mutex::new(Some(worker_handle)),
+			is_shutdown: AtomicBool::default(),
+			backpressure: self.backpressure,
+			strict_lock: self.strict.then(Mutex::default),
+			capacity_lock: Mutex::default(),
+			overhead_manager,
+			removal_listener: self.removal_listener,
+			op_log: None,
+			admission: None,
+
+			loaders: Loaders::default(),
+			hasher: self.hasher,
+		};
+
+		// the log is replayed into the cache before it is attached so that
+		// the replayed operations are not logged again
+		if let Some(open_op_log) = self.op_log {
+			cache.op_log = Some(open_op_log(&cache)?);
+		}
+
+		// the admission policy is also attached after the log is replayed
+		// so that none of the replayed objects are rejected
+		cache.admission = self.admission;
+
+		Ok(cache)
+	}
+}
+
+impl<K, V, S> PaperCacheBuilder<K, V, S>
+where
+	K: 'static + Eq + Hash + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+	V: 'static + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+	S: Default + Clone + BuildHasher,
+{
+	/// Enables an append-only log of the `set`, `del`, `ttl`, and `wipe`
+	/// operations in the supplied directory. When the cache is built, any
+	/// existing log in the directory is replayed to rebuild the cache's
+	/// objects.
+	///
+	/// The log is split into segments which are periodically compacted in
+	/// the background. The [`FsyncPolicy`] determines how often the log is
+	/// synced to disk.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{FsyncPolicy, PaperCache, PaperPolicy};
+	///
+	/// let dir = std::env::temp_dir().join("paper-cache-op-log-example");
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// )
+	/// .op_log(&dir, FsyncPolicy::EverySecond)
+	/// .build();
+	///
+	/// assert!(cache.is_ok());
+	/// # std::fs::remove_dir_all(&dir).ok();
+	/// ```
+	#[must_use]
+	pub fn op_log(mut self, dir: impl AsRef<Path>, fsync: FsyncPolicy) -> Self {
+		let dir = dir.as_ref().to_path_buf();
+
+		self.op_log = Some(Box::new(move |cache| {
+			let op_log = FileOpLog::open(&dir, fsync, cache)?;
+			Ok(op_log as Arc<dyn OpLog<K, V>>)
+		}));
+
+		self
+	}
+}
@@ -38,4 +38,34 @@ pub enum CacheError {
 
 	#[error("invalid policy")]
 	InvalidPolicy,
+
+	#[error("the custom policy has not been registered")]
+	UnregisteredPolicy,
+
+	#[error("the event channel capacity cannot be zero")]
+	ZeroChannelCapacity,
+
+	#[error("the cache has been shut down")]
+	ShutDown,
+
+	#[error("the event queue is full")]
+	QueueFull,
+
+	#[error("the cache is full")]
+	CacheFull,
+
+	#[error("the object was rejected by the admission policy")]
+	Rejected,
+
+	#[error("could not write snapshot")]
+	SnapshotWrite,
+
+	#[error("could not read snapshot")]
+	SnapshotRead,
+
+	#[error("could not write to operation log")]
+	OpLogWrite,
+
+	#[error("could not read operation log")]
+	OpLogRead,
 }
@@ -5,30 +5,67 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+mod admission;
+#[cfg(feature = "async")]
+mod async_cache;
+mod builder;
 mod error;
+mod listener;
+mod loader;
 mod object;
+mod op_log;
+mod options;
 mod policy;
+mod report;
+mod sketch;
+mod snapshot;
 mod status;
 mod worker;
 
 use std::{
 	hash::{BuildHasher, BuildHasherDefault, Hash, RandomState},
-	sync::{Arc, atomic::AtomicU64},
-	thread,
+	mem,
+	sync::{
+		Arc,
+		atomic::{AtomicBool, AtomicU64, Ordering},
+	},
 };
 
-use crossbeam_channel::unbounded;
+use crossbeam_channel::bounded;
 use dashmap::{DashMap, mapref::entry::Entry};
-use kwik::{fmt, math::set::Multiset};
+use kwik::fmt;
 use log::{error, info};
 use nohash_hasher::NoHashHasher;
+use num_traits::AsPrimitive;
+use parking_lot::{Mutex, MutexGuard};
 use typesize::TypeSize;
 
-pub use crate::{error::CacheError, policy::PaperPolicy};
+#[cfg(feature = "async")]
+pub use crate::async_cache::AsyncPaperCache;
+pub use crate::{
+	admission::{Admission, SizeThreshold, TinyLfu},
+	builder::PaperCacheBuilder,
+	error::CacheError,
+	listener::RemovalCause,
+	object::ObjectHints,
+	op_log::FsyncPolicy,
+	options::SetOptions,
+	report::{PolicyEstimate, PolicyReport},
+	policy::{AutoPolicyConfig, AutoPolicyDecision, AutoPolicyObjective, AutoPolicyReason, PaperPolicy},
+	worker::{Backpressure, PolicyStack},
+};
 use crate::{
-	object::{Object, ObjectSize, overhead::OverheadManager},
+	listener::notify_removal,
+	loader::Loaders,
+	object::{
+		ExpireTime,
+		Object,
+		ObjectSize,
+		overhead::{OverheadManager, get_ttl_overhead},
+	},
+	op_log::OpLog,
 	status::{AtomicStatus, Status},
-	worker::{Worker, WorkerEvent, WorkerManager, WorkerSender},
+	worker::{WorkerCallback, WorkerEvent, WorkerHandle, WorkerSender, send_event},
 };
 
 pub type CacheSize = u64;
@@ -40,15 +77,27 @@ pub type NoHasher = BuildHasherDefault<NoHashHasher<HashedKey>>;
 pub type ObjectMapRef<K, V> = Arc<DashMap<HashedKey, Object<K, V>, NoHasher>>;
 pub type StatusRef = Arc<AtomicStatus>;
 pub type OverheadManagerRef = Arc<OverheadManager>;
+pub type RemovalListenerRef<K, V> = Option<Arc<dyn Fn(K, Arc<V>, RemovalCause) + Send + Sync>>;
+pub type OpLogRef<K, V> = Option<Arc<dyn OpLog<K, V>>>;
+pub type AdmissionRef = Option<Arc<dyn Admission>>;
 
 pub struct PaperCache<K, V, S = RandomState> {
 	objects: ObjectMapRef<K, V>,
 	status:  StatusRef,
 
 	worker_manager:   Arc<WorkerSender>,
+	worker_handle:    Mutex<Option<WorkerHandle>>,
+	is_shutdown:      AtomicBool,
+	backpressure:     Backpressure,
+	strict_lock:      Option<Mutex<()>>,
+	capacity_lock:    Mutex<()>,
 	overhead_manager: OverheadManagerRef,
+	removal_listener: RemovalListenerRef<K, V>,
+	op_log:           OpLogRef<K, V>,
+	admission:        AdmissionRef,
 
-	hasher: S,
+	loaders: Loaders,
+	hasher:  S,
 }
 
 impl<K, V, S> PaperCache<K, V, S>
@@ -132,48 +181,34 @@ where
 		policy: PaperPolicy,
 		hasher: S,
 	) -> Result<Self, CacheError> {
-		if max_size == 0 {
-			return Err(CacheError::ZeroCacheSize);
-		}
-
-		if policies.is_empty() {
-			return Err(CacheError::EmptyPolicies);
-		}
-
-		if policies.contains(&PaperPolicy::Auto) {
-			return Err(CacheError::ConfiguredAutoPolicy);
-		}
-
-		if policies.iter().is_multiset() {
-			return Err(CacheError::DuplicatePolicies);
-		}
-
-		if !policy.is_auto() && !policies.contains(&policy) {
-			return Err(CacheError::UnconfiguredPolicy);
-		}
-
-		let objects = Arc::new(DashMap::with_hasher(NoHasher::default()));
-		let status = Arc::new(AtomicStatus::new(max_size, policies, policy)?);
-		let overhead_manager = Arc::new(OverheadManager::new(&status));
-
-		let (worker_sender, worker_listener) = unbounded();
-
-		let mut worker_manager =
-			WorkerManager::new(worker_listener, &objects, &status, &overhead_manager)?;
-
-		thread::spawn(move || worker_manager.run());
-
-		let cache = PaperCache {
-			objects,
-			status,
-
-			worker_manager: Arc::new(worker_sender),
-			overhead_manager,
-
-			hasher,
-		};
+		Self::builder(max_size, policies, policy)
+			.hasher(hasher)
+			.build()
+	}
 
-		Ok(cache)
+	/// Returns a [`PaperCacheBuilder`] for configuring a `PaperCache` with
+	/// maximum size `max_size` and eviction policy `policy`.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::builder(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// )
+	/// .build();
+	///
+	/// assert!(cache.is_ok());
+	/// ```
+	pub fn builder(
+		max_size: CacheSize,
+		policies: &[PaperPolicy],
+		policy: PaperPolicy,
+	) -> PaperCacheBuilder<K, V, S> {
+		PaperCacheBuilder::new(max_size, policies, policy)
 	}
 
 	/// Returns the current cache version.
@@ -216,6 +251,66 @@ where
 		self.status.try_to_status()
 	}
 
+	/// Returns the most recent decisions of the [`PaperPolicy::Auto`] policy,
+	/// from the oldest to the newest. A decision is recorded at the end of
+	/// every epoch, whether or not the cache switched policies.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{AutoPolicyReason, PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu, PaperPolicy::Sieve],
+	///     PaperPolicy::Auto,
+	/// ).unwrap();
+	///
+	/// for decision in cache.auto_policy_history() {
+	///     if decision.reason() == AutoPolicyReason::Switched {
+	///         println!("switched to {}", decision.chosen_policy());
+	///     }
+	/// }
+	/// ```
+	#[must_use]
+	pub fn auto_policy_history(&self) -> Vec<AutoPolicyDecision> {
+		self.status.auto_policy_history()
+	}
+
+	/// Returns the estimated miss ratio and byte miss ratio of each of the
+	/// configured policies, which are simulated on a sample of the cache's
+	/// keys. The report reflects every preceding operation, and never
+	/// switches the cache's policy. See [`PolicyReport`] for the gets which
+	/// the estimates cover.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu, PaperPolicy::Sieve],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// let report = cache.policy_report().unwrap();
+	///
+	/// for estimate in report.estimates() {
+	///     let (lower, upper) = estimate.miss_ratio_interval();
+	///     println!("{}: {lower:.3} to {upper:.3}", estimate.policy());
+	/// }
+	/// ```
+	pub fn policy_report(&self) -> Result<PolicyReport, CacheError> {
+		self.check_shutdown()?;
+
+		let (sender, receiver) = bounded(1);
+		self.broadcast(WorkerEvent::PolicyReport(sender))?;
+
+		receiver.recv().map_err(|err| {
+			error!("Could not receive policy report: {err:?}");
+			CacheError::Internal
+		})
+	}
+
 	/// Gets the value associated with the supplied key.
 	/// If the key was not found in the cache, returns a [`CacheError`].
 	///
@@ -238,28 +333,81 @@ where
 	/// ```
 	pub fn get(&self, key: &K) -> Result<Arc<V>, CacheError> {
 		let hashed_key = self.hash_key(key);
+		let result = self.get_object(key, hashed_key);
 
-		let result = match self.objects.get(&hashed_key) {
-			Some(object) if object.key_matches(key) && !object.is_expired() => {
-				self.status.incr_hits();
-				Ok(object.data())
-			},
+		self.record_access(hashed_key);
 
-			_ => {
-				self.status.incr_misses();
-				Err(CacheError::KeyNotFound)
-			},
-		};
+		match result.is_ok() {
+			true => self.status.incr_hits(),
+			false => self.status.incr_misses(),
+		}
 
 		self.broadcast(WorkerEvent::Get(hashed_key, result.is_ok()))?;
 
 		result
 	}
 
+	/// Gets the values associated with each of the supplied keys.
+	/// Returns one result per key in the same order as the supplied keys,
+	/// where each result is either the associated value or a [`CacheError`]
+	/// if the key was not found in the cache.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let mut cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// cache.set(0, 0, None);
+	///
+	/// let results = cache.mget(&[0, 1]).unwrap();
+	///
+	/// assert!(results[0].is_ok());
+	/// assert!(results[1].is_err());
+	/// ```
+	pub fn mget(&self, keys: &[K]) -> Result<Vec<Result<Arc<V>, CacheError>>, CacheError> {
+		let mut results = Vec::with_capacity(keys.len());
+		let mut events = Vec::with_capacity(keys.len());
+
+		for key in keys {
+			let hashed_key = self.hash_key(key);
+			let result = self.get_object(key, hashed_key);
+
+			self.record_access(hashed_key);
+			events.push(WorkerEvent::Get(hashed_key, result.is_ok()));
+			results.push(result);
+		}
+
+		let hits = results.iter().filter(|result| result.is_ok()).count();
+
+		self.status.incr_hits_by(hits as u64);
+		self.status
+			.incr_misses_by((results.len() - hits) as u64);
+
+		self.broadcast_batch(events)?;
+
+		Ok(results)
+	}
+
 	/// Sets the supplied key and value in the cache.
 	/// Returns a [`CacheError`] if the value size is zero or larger than
 	/// the cache's maximum size.
 	///
+	/// In strict mode, objects are evicted before the value is set until it
+	/// fits in the cache. If it cannot be made to fit, returns
+	/// [`CacheError::CacheFull`].
+	///
+	/// If an [`Admission`] policy is configured and it rejects a key which is
+	/// not already in the cache, returns [`CacheError::Rejected`].
+	///
+	/// If the cache's policy is [`PaperPolicy::NoEviction`], objects are never
+	/// evicted and [`CacheError::CacheFull`] is returned if the value does not
+	/// fit in the cache.
+	///
 	/// If the key already exists in the cache, the associated value is updated
 	/// to the supplied value.
 	///
@@ -276,49 +424,197 @@ where
 	/// assert!(cache.set(0, 0, None).is_ok());
 	/// ```
 	pub fn set(&self, key: K, value: V, ttl: Option<u32>) -> Result<(), CacheError> {
-		let hashed_key = self.hash_key(&key);
+		let event = self.set_object(Object::new(key, value, ttl), ObjectHints::default())?;
 
-		let object = Object::new(key, value, ttl);
-		let base_size = self.overhead_manager.base_size(&object);
-		let expiry = object.expiry();
+		self.status.incr_sets();
+		self.broadcast(event)?;
 
-		if base_size == 0 {
-			return Err(CacheError::ZeroValueSize);
-		}
+		Ok(())
+	}
 
-		if self.status.exceeds_max_size(base_size) {
-			return Err(CacheError::ExceedingValueSize);
-		}
+	/// Sets the supplied key and value in the cache with the supplied
+	/// [`SetOptions`], which include the object's TTL as well as its miss cost
+	/// and priority class for the cost-aware eviction policies. Returns the
+	/// same errors as [`PaperCache::set`].
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy, SetOptions};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Gdsf],
+	///     PaperPolicy::Gdsf,
+	/// ).unwrap();
+	///
+	/// let options = SetOptions::new()
+	///     .cost(500)
+	///     .priority(1);
+	///
+	/// assert!(cache.set_with_options(0, 0, options).is_ok());
+	/// ```
+	pub fn set_with_options(&self, key: K, value: V, options: SetOptions) -> Result<(), CacheError> {
+		let event = self.set_object(Object::new(key, value, options.ttl), options.hints)?;
 
 		self.status.incr_sets();
+		self.broadcast(event)?;
 
-		let old_object_info = self
-			.objects
-			.insert(hashed_key, object)
-			.map(|old_object| {
-				let base_size = self.overhead_manager.base_size(&old_object);
-				let expiry = old_object.expiry();
+		Ok(())
+	}
+
+	/// Sets each of the supplied keys and values in the cache with their
+	/// associated TTLs. Returns one result per object in the same order as
+	/// the supplied objects, where each result is a [`CacheError`] if the
+	/// value size is zero or larger than the cache's maximum size.
+	///
+	/// In strict mode, the objects are set one after another as with
+	/// [`PaperCache::set`], so the batch's earlier objects may be evicted to
+	/// make room for its later ones.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let mut cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// let results = cache.mset([(0, 0, None), (1, 1, Some(5))]).unwrap();
+	///
+	/// assert!(results.iter().all(|result| result.is_ok()));
+	/// assert!(cache.has(&0) && cache.has(&1));
+	/// ```
+	pub fn mset(
+		&self,
+		objects: impl IntoIterator<Item = (K, V, Option<u32>)>,
+	) -> Result<Vec<Result<(), CacheError>>, CacheError> {
+		self.check_shutdown()?;
+
+		let mut results = Vec::new();
+		let mut events = Vec::new();
+		let mut num_sets = 0;
+
+		for (key, value, ttl) in objects {
+			// in strict mode, the policy worker may be asked to evict objects
+			// before this object is set, so it must have already received the
+			// events of the preceding objects
+			if self.strict_lock.is_some() {
+				self.broadcast_batch(mem::take(&mut events))?;
+			}
+
+			let result = self
+				.set_object(Object::new(key, value, ttl), ObjectHints::default())
+				.map(|event| {
+					num_sets += 1;
+					events.push(event);
+				});
+
+			results.push(result);
+		}
 
-				(base_size, expiry)
-			});
+		self.status.incr_sets_by(num_sets);
+		self.broadcast_batch(events)?;
 
-		let base_size_delta = if let Some((old_object_size, _)) = old_object_info {
-			base_size as i64 - old_object_size as i64
-		} else {
-			// the object is new, so increase the number of objects count
-			self.status.incr_num_objects();
-			base_size as i64
+		Ok(results)
+	}
+
+	/// Gets the value associated with the supplied key or, if the key was not
+	/// found in the cache, sets the value returned by the supplied loader with
+	/// the supplied TTL and returns it.
+	///
+	/// When multiple callers miss the same key concurrently, the loader of only
+	/// one of them is run while the others wait for its value.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let mut cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// // The loader is run if the key does not exist in the cache.
+	/// assert_eq!(cache.get_or_insert_with(0, None, || 1).as_deref(), Ok(&1));
+	/// // The loader is not run if the key exists in the cache.
+	/// assert_eq!(cache.get_or_insert_with(0, None, || 2).as_deref(), Ok(&1));
+	/// ```
+	pub fn get_or_insert_with<F>(
+		&self,
+		key: K,
+		ttl: Option<u32>,
+		loader: F,
+	) -> Result<Arc<V>, CacheError>
+	where
+		F: FnOnce() -> V,
+	{
+		self.try_get_or_insert_with(key, ttl, || Ok(loader()))
+	}
+
+	/// Gets the value associated with the supplied key or, if the key was not
+	/// found in the cache, sets the value returned by the supplied fallible
+	/// loader with the supplied TTL and returns it. If the loader fails, its
+	/// error is returned and nothing is set in the cache.
+	///
+	/// When multiple callers miss the same key concurrently, the loader of only
+	/// one of them is run while the others wait for its value. If that loader
+	/// fails, one of the waiting callers runs its own loader instead.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{CacheError, PaperCache, PaperPolicy};
+	///
+	/// let mut cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// let result = cache.try_get_or_insert_with(0, None, || Err(CacheError::Internal));
+	///
+	/// assert!(result.is_err());
+	/// assert!(!cache.has(&0));
+	/// ```
+	pub fn try_get_or_insert_with<F, E>(
+		&self,
+		key: K,
+		ttl: Option<u32>,
+		loader: F,
+	) -> Result<Arc<V>, E>
+	where
+		F: FnOnce() -> Result<V, E>,
+		E: From<CacheError>,
+	{
+		match self.get(&key) {
+			Ok(value) => return Ok(value),
+			Err(CacheError::KeyNotFound) => {},
+			Err(err) => return Err(err.into()),
+		}
+
+		let hashed_key = self.hash_key(&key);
+
+		let _guard = loop {
+			if let Some(guard) = self.loaders.join(hashed_key) {
+				break guard;
+			}
+
+			// another caller finished loading, so its value should now be in
+			// the cache unless the load failed (or the value was evicted)
+			if let Ok(value) = self.peek(&key) {
+				return Ok(value);
+			}
 		};
 
-		self.status.update_base_used_size(base_size_delta);
-		self.broadcast(WorkerEvent::Set(
-			hashed_key,
-			base_size,
-			expiry,
-			old_object_info,
-		))?;
+		// another caller may have finished loading between the miss and
+		// this caller becoming responsible for the load
+		if let Ok(value) = self.peek(&key) {
+			return Ok(value);
+		}
 
-		Ok(())
+		Ok(self.set_loaded(key, loader()?, ttl)?)
 	}
 
 	/// Deletes the object associated with the supplied key in the cache.
@@ -341,21 +637,56 @@ where
 	/// assert!(cache.del(&1).is_err());
 	/// ```
 	pub fn del(&self, key: &K) -> Result<(), CacheError> {
-		let hashed_key = self.hash_key(key);
-
-		let (removed_hashed_key, object) = erase(
-			&self.objects,
-			&self.status,
-			&self.overhead_manager,
-			Some(EraseKey::Original(key, hashed_key)),
-		)?;
+		let event = self.del_object(key)?;
 
 		self.status.incr_dels();
-		self.broadcast(WorkerEvent::Del(removed_hashed_key, object.expiry()))?;
+		self.broadcast(event)?;
 
 		Ok(())
 	}
 
+	/// Deletes the objects associated with each of the supplied keys in the
+	/// cache. Returns one result per key in the same order as the supplied
+	/// keys, where each result is a [`CacheError`] if the key was not found
+	/// in the cache.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let mut cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// cache.set(0, 0, None);
+	///
+	/// let results = cache.mdel(&[0, 1]).unwrap();
+	///
+	/// assert!(results[0].is_ok());
+	/// assert!(results[1].is_err());
+	/// ```
+	pub fn mdel(&self, keys: &[K]) -> Result<Vec<Result<(), CacheError>>, CacheError> {
+		self.check_shutdown()?;
+
+		let mut results = Vec::with_capacity(keys.len());
+		let mut events = Vec::with_capacity(keys.len());
+
+		for key in keys {
+			let result = self
+				.del_object(key)
+				.map(|event| events.push(event));
+
+			results.push(result);
+		}
+
+		self.status.incr_dels_by(events.len() as u64);
+		self.broadcast_batch(events)?;
+
+		Ok(results)
+	}
+
 	/// Checks if an object with the supplied key exists in the cache without
 	/// altering any of the cache's internal queues.
 	///
@@ -412,12 +743,7 @@ where
 	/// ```
 	pub fn peek(&self, key: &K) -> Result<Arc<V>, CacheError> {
 		let hashed_key = self.hash_key(key);
-
-		match self.objects.get(&hashed_key) {
-			Some(object) if object.key_matches(key) && !object.is_expired() => Ok(object.data()),
-
-			_ => Err(CacheError::KeyNotFound),
-		}
+		self.get_object(key, hashed_key)
 	}
 
 	/// Sets the TTL associated with the supplied key.
@@ -437,8 +763,30 @@ where
 	/// cache.ttl(&0, Some(5)); // value will expire in 5 seconds
 	/// ```
 	pub fn ttl(&self, key: &K, ttl: Option<u32>) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+		self.check_backpressure()?;
+
 		let hashed_key = self.hash_key(key);
 
+		// the key's existence is checked before reserving so that a TTL on a
+		// missing key never evicts any objects
+		let has_expiry = match self.objects.get(&hashed_key) {
+			Some(object) if object.key_matches(key) && !object.is_expired() => {
+				object.expiry().is_some()
+			},
+
+			_ => return Err(CacheError::KeyNotFound),
+		};
+
+		// setting a TTL may add the TTL overhead to the object's size
+		let reserved_size = match ttl.is_some_and(|ttl| ttl > 0) && !has_expiry {
+			true => get_ttl_overhead(),
+			false => 0,
+		};
+
+		let _strict_guard = self.reserve(reserved_size)?;
+		let _op_log_guard = self.op_log.as_ref().map(|op_log| op_log.lock(hashed_key));
+
 		let mut object = match self.objects.get_mut(&hashed_key) {
 			Some(object) if object.key_matches(key) && !object.is_expired() => object,
 			_ => return Err(CacheError::KeyNotFound),
@@ -454,6 +802,11 @@ where
 
 		self.status
 			.update_base_used_size(new_base_size as i64 - old_base_size as i64);
+
+		if let Some(op_log) = &self.op_log {
+			op_log.ttl(key, new_expiry)?;
+		}
+
 		self.broadcast(WorkerEvent::Ttl(hashed_key, old_expiry, new_expiry))?;
 
 		Ok(())
@@ -507,10 +860,44 @@ where
 	/// cache.wipe();
 	/// ```
 	pub fn wipe(&self) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+		self.check_backpressure()?;
+
 		info!("Wiping cache");
 
-		self.objects.clear();
-		self.status.clear();
+		let removed_objects = {
+			let _op_log_guard = self.op_log.as_ref().map(|op_log| op_log.lock_all());
+
+			let removed_objects = if self.removal_listener.is_some() {
+				// the objects are removed one at a time so that each of them can be
+				// passed to the removal listener
+				let keys = self
+					.objects
+					.iter()
+					.map(|object| *object.key())
+					.collect::<Vec<_>>();
+
+				keys.into_iter()
+					.filter_map(|key| self.objects.remove(&key))
+					.map(|(_, object)| object)
+					.collect::<Vec<_>>()
+			} else {
+				self.objects.clear();
+				Vec::new()
+			};
+
+			self.status.clear();
+
+			if let Some(op_log) = &self.op_log {
+				op_log.wipe()?;
+			}
+
+			removed_objects
+		};
+
+		for object in removed_objects {
+			notify_removal(&self.removal_listener, object, RemovalCause::Wiped);
+		}
 
 		self.broadcast(WorkerEvent::Wipe)?;
 
@@ -520,6 +907,10 @@ where
 	/// Resizes the cache to the supplied maximum size.
 	/// If the supplied size is zero, returns a [`CacheError`].
 	///
+	/// If the cache's policy is [`PaperPolicy::NoEviction`] and the supplied
+	/// size is less than the cache's used size, returns
+	/// [`CacheError::CacheFull`].
+	///
 	/// # Examples
 	/// ```
 	/// use paper_cache::{PaperCache, PaperPolicy};
@@ -536,22 +927,41 @@ where
 	/// assert!(cache.resize(0).is_err());
 	/// ```
 	pub fn resize(&self, max_size: CacheSize) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+
 		if max_size == 0 {
 			return Err(CacheError::ZeroCacheSize);
 		}
 
+		self.check_backpressure()?;
+
+		// the locks are acquired in the same order as in sets
+		let _capacity_guard = self.capacity_lock.lock();
+		let _strict_guard = self.strict_lock.as_ref().map(Mutex::lock);
 		let current_max_size = self.status.max_size();
 
 		if max_size == current_max_size {
 			return Ok(());
 		}
 
+		let policy = self.status.policy();
+
+		// objects are never evicted to make room, so the cache cannot shrink
+		// below the size of the objects it already holds
+		if policy.is_no_eviction() && max_size < self.status.used_size(&policy) {
+			return Err(CacheError::CacheFull);
+		}
+
 		info!(
 			"Resizing cache from {} to {}",
 			fmt::memory(current_max_size, Some(2)),
 			fmt::memory(max_size, Some(2)),
 		);
 
+		if self.strict_lock.is_some() && max_size < current_max_size {
+			self.evict_to(max_size)?;
+		}
+
 		self.status.set_max_size(max_size);
 		self.broadcast(WorkerEvent::Resize(max_size))?;
 
@@ -560,6 +970,10 @@ where
 
 	/// Sets the eviction policy of the cache to the supplied policy.
 	///
+	/// Since objects are never evicted with [`PaperPolicy::NoEviction`],
+	/// switching to it while the cache holds more than its maximum size
+	/// returns [`CacheError::CacheFull`].
+	///
 	/// # Examples
 	/// ```
 	/// use paper_cache::{PaperCache, PaperPolicy};
@@ -574,138 +988,1611 @@ where
 	/// assert!(cache.policy(PaperPolicy::Lru).is_err());
 	/// ```
 	pub fn policy(&self, policy: PaperPolicy) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+
 		if !policy.is_auto() && !self.status.policies().contains(&policy) {
 			return Err(CacheError::UnconfiguredPolicy);
 		}
 
-		self.status.set_policy(policy)?;
-		self.broadcast(WorkerEvent::Policy(policy))?;
+		self.check_backpressure()?;
 
-		Ok(())
-	}
+		// switching to a policy with a larger overhead increases the size
+		// of every object in the cache
+		let reserved_size = match policy.is_auto() {
+			true => 0,
+
+			false => {
+				let current_overhead = self.status.policy_overhead(&self.status.policy());
+				let overhead_delta = self
+					.status
+					.policy_overhead(&policy)
+					.saturating_sub(current_overhead);
+
+				self.status.num_objects() * overhead_delta as CacheSize
+			},
+		};
+
+		// the locks are acquired in the same order as in sets
+		let _capacity_guard = self.capacity_lock.lock();
+		let _strict_guard = self.reserve(reserved_size)?;
+
+		// objects are never evicted to make room, so the cache cannot switch
+		// to never evicting while it holds more than its maximum size
+		if policy.is_no_eviction() && self.status.used_size(&policy) > self.status.max_size() {
+			return Err(CacheError::CacheFull);
+		}
+
+		self.status.set_policy(policy)?;
+		self.broadcast(WorkerEvent::Policy(policy))?;
+
+		Ok(())
+	}
+
+	/// Parses the supplied value into a [`PaperPolicy`]. Unlike parsing with
+	/// [`str::parse`], which only recognizes the built-in policies, the names
+	/// of the custom policies registered with this cache are also recognized.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// assert_eq!(cache.parse_policy("lru"), Ok(PaperPolicy::Lru));
+	/// assert!(cache.parse_policy("newest").is_err());
+	/// ```
+	pub fn parse_policy(&self, value: &str) -> Result<PaperPolicy, CacheError> {
+		self.status.custom_policies().parse(value)
+	}
+
+	/// Changes how the [`PaperPolicy::Auto`] policy selects one of the
+	/// configured policies. Changing the sample rate clears the simulated
+	/// policies, which are then refilled with the newly sampled keys.
+	///
+	/// # Examples
+	/// ```
+	/// use std::time::Duration;
+	///
+	/// use paper_cache::{AutoPolicyConfig, PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu, PaperPolicy::Lru],
+	///     PaperPolicy::Auto,
+	/// ).unwrap();
+	///
+	/// let config = AutoPolicyConfig::new()
+	///     .epoch(Duration::from_secs(60))
+	///     .min_improvement(0.05);
+	///
+	/// assert!(cache.configure_auto_policy(config).is_ok());
+	/// ```
+	pub fn configure_auto_policy(&self, config: AutoPolicyConfig) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+		self.check_backpressure()?;
+		self.broadcast(WorkerEvent::AutoPolicy(config))
+	}
+
+	/// Blocks until all preceding operations have been fully applied by the
+	/// cache's workers, including any policy switches and resizes.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let mut cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu, PaperPolicy::Lru],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// cache.policy(PaperPolicy::Lru);
+	///
+	/// // The Lru stack has been reconstructed once settled.
+	/// assert!(cache.settle().is_ok());
+	/// ```
+	pub fn settle(&self) -> Result<(), CacheError> {
+		let (sender, receiver) = bounded(1);
+
+		self.settle_with(Arc::new(move || {
+			sender.try_send(()).ok();
+		}))?;
+
+		// the callback (and therefore the sender) is dropped without being
+		// invoked if the workers are no longer running
+		receiver.recv().map_err(|_| CacheError::Internal)
+	}
+
+	fn get_object(&self, key: &K, hashed_key: HashedKey) -> Result<Arc<V>, CacheError> {
+		match self.objects.get(&hashed_key) {
+			Some(object) if object.key_matches(key) && !object.is_expired() => Ok(object.data()),
+			_ => Err(CacheError::KeyNotFound),
+		}
+	}
+
+	fn set_object(&self, object: Object<K, V>, hints: ObjectHints) -> Result<WorkerEvent, CacheError> {
+		self.check_shutdown()?;
+
+		let hashed_key = self.hash_key(object.key());
+		let base_size = self.overhead_manager.base_size(&object);
+		let expiry = object.expiry();
+
+		if base_size == 0 {
+			return Err(CacheError::ZeroValueSize);
+		}
+
+		if self.status.exceeds_max_size(base_size) {
+			return Err(CacheError::ExceedingValueSize);
+		}
+
+		if !self.is_admitted(hashed_key, &object) {
+			self.status.incr_rejected_sets();
+			return Err(CacheError::Rejected);
+		}
+
+		self.check_backpressure()?;
+		let capacity_guard = self.check_capacity(hashed_key, &object)?;
+
+		// the replaced object's size is not subtracted since it may be
+		// removed concurrently
+		let strict_guard = self.reserve(self.overhead_manager.total_size(&object))?;
+
+		let old_object = {
+			let _op_log_guard = self.op_log.as_ref().map(|op_log| op_log.lock(hashed_key));
+
+			// the operation is logged before it is applied so that it is never
+			// missing from the log once visible in the cache
+			if let Some(op_log) = &self.op_log {
+				op_log.set(object.key(), &object.data(), expiry)?;
+			}
+
+			self.objects.insert(hashed_key, object)
+		};
+
+		let old_object_info = old_object.as_ref().map(|old_object| {
+			let base_size = self.overhead_manager.base_size(old_object);
+			(base_size, old_object.expiry())
+		});
+
+		let base_size_delta = if let Some((old_object_size, _)) = old_object_info {
+			base_size as i64 - old_object_size as i64
+		} else {
+			// the object is new, so increase the number of objects count
+			self.status.incr_num_objects();
+			base_size as i64
+		};
+
+		self.status.update_base_used_size(base_size_delta);
+
+		// the listener is notified once the locks are released so that it
+		// can safely access the cache
+		drop(capacity_guard);
+		drop(strict_guard);
+
+		if let Some(old_object) = old_object {
+			notify_removal(&self.removal_listener, old_object, RemovalCause::Replaced);
+		}
+
+		Ok(WorkerEvent::Set(
+			hashed_key,
+			base_size,
+			expiry,
+			hints,
+			old_object_info,
+		))
+	}
+
+	fn set_loaded(&self, key: K, value: V, ttl: Option<u32>) -> Result<Arc<V>, CacheError> {
+		let object = Object::new(key, value, ttl);
+		let value = object.data();
+
+		// a rejected value is still returned to the caller which loaded it
+		let event = match self.set_object(object, ObjectHints::default()) {
+			Ok(event) => event,
+			Err(CacheError::Rejected) => return Ok(value),
+			Err(err) => return Err(err),
+		};
+
+		self.status.incr_sets();
+		self.broadcast(event)?;
+
+		Ok(value)
+	}
+
+	fn del_object(&self, key: &K) -> Result<WorkerEvent, CacheError> {
+		self.check_shutdown()?;
+		self.check_backpressure()?;
+
+		let hashed_key = self.hash_key(key);
+
+		let object = {
+			let _op_log_guard = self.op_log.as_ref().map(|op_log| op_log.lock(hashed_key));
+
+			let (_, object) = take(
+				&self.objects,
+				&self.status,
+				&self.overhead_manager,
+				Some(EraseKey::Original(key, hashed_key)),
+			)?;
+
+			// an expired object is not logged since it is already expired
+			// when the log is replayed
+			if let Some(op_log) = &self.op_log
+				&& !object.is_expired()
+			{
+				op_log.del(key)?;
+			}
+
+			object
+		};
+
+		let expiry = object.expiry();
+		let is_expired = object.is_expired();
+
+		notify_removal(&self.removal_listener, object, RemovalCause::Deleted);
+
+		match !is_expired {
+			true => Ok(WorkerEvent::Del(hashed_key, expiry)),
+			false => Err(CacheError::KeyNotFound),
+		}
+	}
+
+	/// Returns [`CacheError::ShutDown`] if the cache has been shut down. This
+	/// is checked before an operation is applied so that an operation whose
+	/// event could never be sent leaves the cache unchanged.
+	fn check_shutdown(&self) -> Result<(), CacheError> {
+		match self.is_shutdown.load(Ordering::Acquire) {
+			true => Err(CacheError::ShutDown),
+			false => Ok(()),
+		}
+	}
 
 	fn broadcast(&self, event: WorkerEvent) -> Result<(), CacheError> {
-		if let Err(err) = self.worker_manager.try_send(event) {
-			error!("Could not communicate with workers: {err:?}");
+		send_event(&self.worker_manager, event, &self.status)
+	}
+
+	/// Returns a [`CacheError`] if the cache fails fast and the workers' event
+	/// queue is full. This is checked before an operation is applied so that
+	/// the operation's event is never lost, which means the queue may fill up
+	/// again before the event is sent (in which case the send blocks).
+	fn check_backpressure(&self) -> Result<(), CacheError> {
+		if self.backpressure == Backpressure::FailFast && self.worker_manager.is_full() {
+			return Err(CacheError::QueueFull);
+		}
+
+		Ok(())
+	}
+
+	/// Returns [`CacheError::CacheFull`] if the cache's policy never evicts
+	/// objects and the supplied object does not fit in the cache. Otherwise,
+	/// returns a guard which must be held until the object's size has been
+	/// added to the used size so that concurrent sets cannot both fit in the
+	/// same space, or `None` if the cache's policy evicts objects.
+	fn check_capacity(
+		&self,
+		hashed_key: HashedKey,
+		object: &Object<K, V>,
+	) -> Result<Option<MutexGuard<'_, ()>>, CacheError> {
+		let policy = self.status.policy();
+
+		if !policy.is_no_eviction() {
+			return Ok(None);
+		}
+
+		let guard = self.capacity_lock.lock();
+
+		// the space of the replaced object (if any) is reused by the new object
+		let replaced_size = self
+			.objects
+			.get(&hashed_key)
+			.map(|old_object| self.overhead_manager.total_size(old_object.value()))
+			.unwrap_or_default();
+
+		let used_size = self
+			.status
+			.used_size(&policy)
+			.saturating_sub(replaced_size.into());
+
+		let size = self.overhead_manager.total_size(object);
+
+		if used_size + CacheSize::from(size) > self.status.max_size() {
+			return Err(CacheError::CacheFull);
+		}
+
+		Ok(Some(guard))
+	}
+
+	/// Returns `true` if the admission policy (if one is configured) admits
+	/// the supplied object. Objects which replace an existing object are
+	/// always admitted.
+	fn is_admitted(&self, hashed_key: HashedKey, object: &Object<K, V>) -> bool {
+		let Some(admission) = &self.admission else {
+			return true;
+		};
+
+		if self.objects.contains_key(&hashed_key) {
+			return true;
+		}
+
+		let size = self.overhead_manager.total_size(object);
+		let used_size = self.status.used_size(&self.status.policy());
+		let available_size = self.status.max_size().saturating_sub(used_size);
+
+		admission.admit(
+			hashed_key,
+			size,
+			available_size,
+			self.status.last_eviction(),
+		)
+	}
+
+	fn record_access(&self, hashed_key: HashedKey) {
+		if let Some(admission) = &self.admission {
+			admission.record(hashed_key);
+		}
+	}
+
+	/// In strict mode, evicts objects until an additional object of the
+	/// supplied size fits in the cache, returning a guard which must be held
+	/// until the object's size has been added to the used size. Returns
+	/// `None` if the cache is not in strict mode.
+	fn reserve(
+		&self,
+		size: impl AsPrimitive<CacheSize>,
+	) -> Result<Option<MutexGuard<'_, ()>>, CacheError> {
+		let Some(strict_lock) = &self.strict_lock else {
+			return Ok(None);
+		};
+
+		let guard = strict_lock.lock();
+		let target_size = self.status.max_size().saturating_sub(size.as_());
+
+		if self.status.used_size(&self.status.policy()) > target_size {
+			self.evict_to(target_size)?;
+		}
+
+		Ok(Some(guard))
+	}
+
+	/// Blocks until the policy worker has evicted objects such that the used
+	/// size is at most the supplied size. Returns a [`CacheError`] if the used
+	/// size could not be reduced enough.
+	fn evict_to(&self, target_size: CacheSize) -> Result<(), CacheError> {
+		let (sender, receiver) = bounded(1);
+
+		self.broadcast(WorkerEvent::Evict(target_size, sender))?;
+
+		if receiver.recv().is_err() {
+			error!("Could not receive eviction confirmation from the policy worker");
 			return Err(CacheError::Internal);
 		}
 
-		Ok(())
+		if self.status.used_size(&self.status.policy()) > target_size {
+			return Err(CacheError::CacheFull);
+		}
+
+		Ok(())
+	}
+
+	/// Invokes the supplied callback once the workers have fully applied all
+	/// preceding events, including any policy switches and resizes.
+	fn settle_with(&self, callback: WorkerCallback) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+		self.broadcast(WorkerEvent::Settle(callback))
+	}
+
+	/// Sends the supplied events to the workers as a single message through
+	/// each of the worker channels rather than one message per event.
+	///
+	/// The events are only seen by the workers once the batch is sent, so the
+	/// batch must be sent before waiting on the workers for anything which
+	/// depends on them (such as an eviction in strict mode).
+	fn broadcast_batch(&self, events: Vec<WorkerEvent>) -> Result<(), CacheError> {
+		if events.is_empty() {
+			return Ok(());
+		}
+
+		self.broadcast(WorkerEvent::Batch(events.into()))
+	}
+
+	fn hash_key(&self, key: &K) -> HashedKey {
+		self.hasher.hash_one(key)
+	}
+}
+
+impl<K, V, S> PaperCache<K, V, S> {
+	/// Shuts down the cache's workers once they have applied all the pending
+	/// operations, waiting for their threads to finish and releasing their
+	/// resources. Subsequent operations which modify the cache (or wait for
+	/// its workers) return [`CacheError::ShutDown`] without being applied,
+	/// while the objects can still be read. If the cache has an operation
+	/// log, its background worker is also shut down and the log is synced to
+	/// disk.
+	///
+	/// The cache is automatically shut down when it is dropped, so calling
+	/// this is only necessary to handle any errors which occur during the
+	/// shutdown. Shutting down an already shut down cache does nothing.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// cache.set(0, 0, None);
+	///
+	/// assert!(cache.shutdown().is_ok());
+	/// assert!(cache.set(1, 1, None).is_err());
+	/// ```
+	pub fn shutdown(&self) -> Result<(), CacheError> {
+		// operations are rejected before the workers stop so that none of
+		// them are applied without being sent to the workers
+		self.is_shutdown.store(true, Ordering::Release);
+
+		let Some(worker_handle) = self.worker_handle.lock().take() else {
+			return Ok(());
+		};
+
+		info!("Shutting down cache");
+
+		let result = worker_handle.shutdown();
+
+		if let Some(op_log) = &self.op_log {
+			op_log.shutdown()?;
+		}
+
+		result
+	}
+}
+
+impl<K, V, S> Drop for PaperCache<K, V, S> {
+	fn drop(&mut self) {
+		if let Err(err) = self.shutdown() {
+			error!("Could not shut down cache: {err:?}");
+		}
+	}
+}
+
+pub enum EraseKey<'a, K> {
+	Original(&'a K, HashedKey),
+	Hashed(HashedKey),
+}
+
+pub fn erase<K, V>(
+	objects: &ObjectMapRef<K, V>,
+	status: &StatusRef,
+	overhead_manager: &OverheadManagerRef,
+	removal_listener: &RemovalListenerRef<K, V>,
+	maybe_key: Option<EraseKey<K>>,
+	cause: RemovalCause,
+) -> Result<(HashedKey, ExpireTime), CacheError>
+where
+	K: Eq + TypeSize,
+	V: TypeSize,
+{
+	let (hashed_key, object) = take(objects, status, overhead_manager, maybe_key)?;
+
+	let expiry = object.expiry();
+	let is_expired = object.is_expired();
+
+	notify_removal(removal_listener, object, cause);
+
+	match !is_expired {
+		true => Ok((hashed_key, expiry)),
+		false => Err(CacheError::KeyNotFound),
+	}
+}
+
+/// Removes the object from the cache and returns it without notifying the
+/// removal listener, including an expired object.
+fn take<K, V>(
+	objects: &ObjectMapRef<K, V>,
+	status: &StatusRef,
+	overhead_manager: &OverheadManagerRef,
+	maybe_key: Option<EraseKey<K>>,
+) -> Result<(HashedKey, Object<K, V>), CacheError>
+where
+	K: Eq + TypeSize,
+	V: TypeSize,
+{
+	let hashed_key = match maybe_key {
+		Some(EraseKey::Original(_, hashed_key)) => hashed_key,
+		Some(EraseKey::Hashed(hashed_key)) => hashed_key,
+
+		None => {
+			// the policy has run out of keys to evict (either it's a mini stack or
+			// something went wrong during policy reconstruction) so we fall back
+			// to evicting a random object
+
+			let Some(object) = objects.iter().next() else {
+				error!("Object store is empty with non-zero used size");
+				return Err(CacheError::Internal);
+			};
+
+			object.key().to_owned()
+		},
+	};
+
+	// don't remove the object right away because if we have the original key,
+	// we need to do a validation check that it matches the object's key in
+	// case of a hash collision
+	let Entry::Occupied(entry) = objects.entry(hashed_key) else {
+		return Err(CacheError::KeyNotFound);
+	};
+
+	if let Some(EraseKey::Original(key, _)) = maybe_key
+		&& !entry.get().key_matches(key)
+	{
+		return Err(CacheError::KeyNotFound);
+	};
+
+	let object = entry.remove();
+	let base_size = overhead_manager.base_size(&object) as i64;
+
+	status.update_base_used_size(-base_size);
+	status.decr_num_objects();
+
+	Ok((hashed_key, object))
+}
+
+unsafe impl<K, V, S> Send for PaperCache<K, V, S> {}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use crate::{
+		AutoPolicyConfig,
+		AutoPolicyReason,
+		CacheError,
+		HashedKey,
+		PaperCache,
+		PaperPolicy,
+		PolicyStack,
+		SetOptions,
+		object::{ExpireTime, ObjectSize},
+	};
+
+	const TEST_CACHE_MAX_SIZE: u64 = 1000;
+
+	#[test]
+	fn it_returns_correct_version() {
+		let cache = init_test_cache();
+		assert_eq!(cache.version(), env!("CARGO_PKG_VERSION"));
+	}
+
+	#[test]
+	fn it_returns_status() {
+		let cache = init_test_cache();
+		let status = cache.status().unwrap();
+
+		assert_eq!(status.max_size(), TEST_CACHE_MAX_SIZE);
+	}
+
+	#[test]
+	fn it_gets_an_existing_object() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert_eq!(cache.get(&0).as_deref(), Ok(&1));
+	}
+
+	#[test]
+	fn it_does_not_get_a_non_existing_object() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert_eq!(cache.get(&1), Err(CacheError::KeyNotFound));
+	}
+
+	#[test]
+	fn it_calculates_miss_ratio_correctly() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.get(&0).is_ok());
+		assert!(cache.get(&0).is_ok());
+		assert!(cache.get(&0).is_ok());
+		assert!(cache.get(&1).is_err());
+
+		let status = cache.status().unwrap();
+		assert_eq!(status.miss_ratio(), 0.25);
+	}
+
+	#[test]
+	fn it_mgets_existing_and_non_existing_objects() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(2, 3, None).is_ok());
+
+		let results = cache.mget(&[0, 1, 2, 3]).unwrap();
+
+		assert_eq!(results.len(), 4);
+		assert_eq!(results[0].as_deref(), Ok(&1));
+		assert_eq!(results[1], Err(CacheError::KeyNotFound));
+		assert_eq!(results[2].as_deref(), Ok(&3));
+		assert_eq!(results[3], Err(CacheError::KeyNotFound));
+
+		let status = cache.status().unwrap();
+		assert_eq!(status.total_gets(), 4);
+		assert_eq!(status.miss_ratio(), 0.5);
+	}
+
+	#[test]
+	fn it_msets_objects() {
+		let cache = init_test_cache();
+
+		let results = cache
+			.mset([(0, 1, None), (1, 2, Some(5)), (2, 3, None)])
+			.unwrap();
+
+		assert!(results.iter().all(|result| result.is_ok()));
+
+		assert_eq!(cache.get(&0).as_deref(), Ok(&1));
+		assert_eq!(cache.get(&1).as_deref(), Ok(&2));
+		assert_eq!(cache.get(&2).as_deref(), Ok(&3));
+
+		let status = cache.status().unwrap();
+		assert_eq!(status.total_sets(), 3);
+		assert_eq!(status.num_objects(), 3);
+	}
+
+	#[test]
+	fn it_mdels_existing_and_non_existing_objects() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(2, 3, None).is_ok());
+
+		let results = cache.mdel(&[0, 1, 2]).unwrap();
+
+		assert_eq!(results, vec![Ok(()), Err(CacheError::KeyNotFound), Ok(())]);
+		assert!(!cache.has(&0) && !cache.has(&2));
+
+		let status = cache.status().unwrap();
+		assert_eq!(status.total_dels(), 2);
+		assert_eq!(status.num_objects(), 0);
+	}
+
+	#[test]
+	fn it_gets_or_inserts_a_non_existing_object() {
+		let cache = init_test_cache();
+
+		assert_eq!(cache.get_or_insert_with(0, None, || 1).as_deref(), Ok(&1));
+		assert_eq!(cache.get(&0).as_deref(), Ok(&1));
+	}
+
+	#[test]
+	fn it_gets_or_inserts_an_existing_object() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert_eq!(cache.get_or_insert_with(0, None, || 2).as_deref(), Ok(&1));
+	}
+
+	#[test]
+	fn it_does_not_insert_a_failed_load() {
+		let cache = init_test_cache();
+
+		let result = cache.try_get_or_insert_with(0, None, || Err(CacheError::Internal));
+
+		assert_eq!(result, Err(CacheError::Internal));
+		assert!(!cache.has(&0));
+	}
+
+	#[test]
+	fn it_loads_a_concurrently_missed_object_once() {
+		use std::{
+			sync::{
+				Arc,
+				atomic::{AtomicU32, Ordering},
+			},
+			thread,
+			time::Duration,
+		};
+
+		let cache = Arc::new(init_test_cache());
+		let num_loads = Arc::new(AtomicU32::new(0));
+
+		let handles = (0..8)
+			.map(|_| {
+				let cache = cache.clone();
+				let num_loads = num_loads.clone();
+
+				thread::spawn(move || {
+					cache.get_or_insert_with(0, None, || {
+						thread::sleep(Duration::from_millis(100));
+						num_loads.fetch_add(1, Ordering::Relaxed) + 1
+					})
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for handle in handles {
+			assert_eq!(handle.join().unwrap().as_deref(), Ok(&1));
+		}
+
+		assert_eq!(num_loads.load(Ordering::Relaxed), 1);
+	}
+
+	#[test]
+	fn it_notifies_the_removal_listener() {
+		use std::sync::{Arc, Mutex};
+
+		use crate::RemovalCause;
+
+		let removals = Arc::new(Mutex::new(Vec::new()));
+		let listener_removals = removals.clone();
+
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.listener(move |key, value, cause| {
+			listener_removals
+				.lock()
+				.unwrap()
+				.push((key, *value, cause));
+		})
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(0, 2, None).is_ok());
+		assert!(cache.del(&0).is_ok());
+		assert!(cache.set(1, 3, None).is_ok());
+		assert!(cache.wipe().is_ok());
+
+		assert_eq!(*removals.lock().unwrap(), vec![
+			(0, 1, RemovalCause::Replaced),
+			(0, 2, RemovalCause::Deleted),
+			(1, 3, RemovalCause::Wiped),
+		]);
+	}
+
+	#[test]
+	fn it_notifies_the_removal_listener_of_evictions() {
+		use std::sync::{Arc, Mutex};
+
+		use crate::RemovalCause;
+
+		let removals = Arc::new(Mutex::new(Vec::new()));
+		let listener_removals = removals.clone();
+
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lru],
+			PaperPolicy::Lru,
+		)
+		.listener(move |key, _, cause| {
+			listener_removals.lock().unwrap().push((key, cause));
+		})
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 1, None).is_ok());
+		assert!(cache.settle().is_ok());
+
+		assert!(cache.resize(1).is_ok());
+		assert!(cache.settle().is_ok());
+
+		assert_eq!(*removals.lock().unwrap(), vec![
+			(0, RemovalCause::Evicted),
+			(1, RemovalCause::Evicted),
+		]);
+	}
+
+	#[test]
+	fn it_notifies_the_removal_listener_of_expirations() {
+		use std::{
+			sync::{Arc, Mutex},
+			thread,
+			time::Duration,
+		};
+
+		use crate::RemovalCause;
+
+		let removals = Arc::new(Mutex::new(Vec::new()));
+		let listener_removals = removals.clone();
+
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.listener(move |key, _, cause| {
+			listener_removals.lock().unwrap().push((key, cause));
+		})
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, Some(1)).is_ok());
+		thread::sleep(Duration::from_secs(2));
+
+		assert_eq!(*removals.lock().unwrap(), vec![(0, RemovalCause::Expired)]);
+	}
+
+	#[test]
+	fn it_restores_a_snapshot() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 2, Some(100)).is_ok());
+		assert!(cache.get(&0).is_ok());
+		assert!(cache.get(&2).is_err());
+
+		let mut snapshot = Vec::new();
+		assert!(cache.save_snapshot(&mut snapshot).is_ok());
+
+		let restored = init_test_cache();
+		assert!(restored.load_snapshot(snapshot.as_slice()).is_ok());
+
+		assert_eq!(restored.peek(&0), Ok(1.into()));
+		assert_eq!(restored.peek(&1), Ok(2.into()));
+
+		assert_eq!(
+			restored.status().unwrap().miss_ratio(),
+			cache.status().unwrap().miss_ratio(),
+		);
+
+		assert_eq!(
+			restored.status().unwrap().total_sets(),
+			cache.status().unwrap().total_sets(),
+		);
+	}
+
+	#[test]
+	fn it_restores_the_ttl_from_a_snapshot() {
+		use std::{thread, time::Duration};
+
+		let cache = init_test_cache();
+		assert!(cache.set(0, 1, Some(3)).is_ok());
+
+		let mut snapshot = Vec::new();
+		assert!(cache.save_snapshot(&mut snapshot).is_ok());
+
+		let restored = init_test_cache();
+		assert!(restored.load_snapshot(snapshot.as_slice()).is_ok());
+		assert!(restored.get(&0).is_ok());
+
+		thread::sleep(Duration::from_secs(4));
+		assert!(restored.get(&0).is_err());
+	}
+
+	#[test]
+	fn it_restores_the_eviction_order_from_a_snapshot() {
+		use std::{thread, time::Duration};
+
+		let cache = PaperCache::<u32, u32>::new(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lru],
+			PaperPolicy::Lru,
+		)
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 1, None).is_ok());
+		assert!(cache.set(2, 1, None).is_ok());
+		assert!(cache.get(&0).is_ok());
+
+		// wait for the accesses to be traced
+		thread::sleep(Duration::from_secs(2));
+
+		let mut snapshot = Vec::new();
+		assert!(cache.save_snapshot(&mut snapshot).is_ok());
+
+		let restored = PaperCache::<u32, u32>::new(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lru],
+			PaperPolicy::Lru,
+		)
+		.expect("Could not initialize test cache");
+
+		assert!(restored.load_snapshot(snapshot.as_slice()).is_ok());
+		assert!(restored.settle().is_ok());
+
+		let object_size = restored.size(&0).unwrap();
+		assert!(restored.resize(object_size.into()).is_ok());
+		assert!(restored.settle().is_ok());
+
+		assert!(restored.has(&0));
+		assert!(!restored.has(&1));
+		assert!(!restored.has(&2));
+	}
+
+	#[test]
+	fn it_skips_snapshot_objects_which_exceed_the_capacity_with_no_eviction() {
+		let cache = PaperCache::<u32, u32>::new(
+			TEST_CACHE_MAX_SIZE * 10,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.expect("Could not initialize test cache");
+
+		for key in 0..100 {
+			assert!(cache.set(key, key, None).is_ok());
+		}
+
+		let mut snapshot = Vec::new();
+		assert!(cache.save_snapshot(&mut snapshot).is_ok());
+
+		let restored = PaperCache::<u32, u32>::new(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::NoEviction],
+			PaperPolicy::NoEviction,
+		)
+		.expect("Could not initialize test cache");
+
+		assert!(restored.load_snapshot(snapshot.as_slice()).is_ok());
+		assert!(restored.settle().is_ok());
+
+		let num_objects = (0..100).filter(|key| restored.has(key)).count();
+		assert!(num_objects > 0 && num_objects < 100);
+	}
+
+	#[test]
+	fn it_does_not_load_an_invalid_snapshot() {
+		let cache = init_test_cache();
+
+		assert_eq!(
+			cache.load_snapshot([1, 2, 3].as_slice()),
+			Err(CacheError::SnapshotRead),
+		);
+	}
+
+	#[test]
+	fn it_replays_the_op_log() {
+		let dir = tempfile::tempdir().expect("Could not create temp dir");
+
+		let cache = init_test_op_log_cache(dir.path());
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 2, None).is_ok());
+		assert!(cache.set(2, 3, None).is_ok());
+		assert!(cache.set(0, 4, None).is_ok());
+		assert!(cache.del(&1).is_ok());
+		assert!(cache.ttl(&2, Some(100)).is_ok());
+
+		drop(cache);
+
+		let restored = init_test_op_log_cache(dir.path());
+
+		assert_eq!(restored.get(&0), Ok(4.into()));
+		assert_eq!(restored.get(&1), Err(CacheError::KeyNotFound));
+		assert_eq!(restored.get(&2), Ok(3.into()));
+		assert_eq!(restored.status().unwrap().total_sets(), 0);
+	}
+
+	#[test]
+	fn it_replays_a_wipe_from_the_op_log() {
+		let dir = tempfile::tempdir().expect("Could not create temp dir");
+
+		let cache = init_test_op_log_cache(dir.path());
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.wipe().is_ok());
+		assert!(cache.set(1, 2, None).is_ok());
+
+		drop(cache);
+
+		let restored = init_test_op_log_cache(dir.path());
+
+		assert!(!restored.has(&0));
+		assert!(restored.has(&1));
+	}
+
+	#[test]
+	fn it_replays_a_compacted_op_log() {
+		let dir = tempfile::tempdir().expect("Could not create temp dir");
+
+		let cache = init_test_op_log_cache(dir.path());
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 2, None).is_ok());
+		assert!(cache.op_log.as_ref().unwrap().compact().is_ok());
+		assert!(cache.del(&0).is_ok());
+		assert!(cache.set(2, 3, None).is_ok());
+
+		drop(cache);
+
+		let restored = init_test_op_log_cache(dir.path());
+
+		assert!(!restored.has(&0));
+		assert_eq!(restored.get(&1), Ok(2.into()));
+		assert_eq!(restored.get(&2), Ok(3.into()));
+	}
+
+	#[test]
+	fn it_skips_replayed_objects_which_exceed_the_capacity_with_no_eviction() {
+		use crate::FsyncPolicy;
+
+		let dir = tempfile::tempdir().expect("Could not create temp dir");
+
+		let cache = init_test_op_log_cache(dir.path());
+
+		// the evictions are not logged, so the log holds more objects than
+		// fit in the cache
+		for key in 0..100 {
+			assert!(cache.set(key, key, None).is_ok());
+		}
+
+		drop(cache);
+
+		let restored = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::NoEviction],
+			PaperPolicy::NoEviction,
+		)
+		.op_log(dir.path(), FsyncPolicy::Always)
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert!(restored.status().unwrap().used_size() <= TEST_CACHE_MAX_SIZE);
+		assert!(restored.has(&0));
+	}
+
+	#[test]
+	fn it_shuts_down_with_an_op_log() {
+		let dir = tempfile::tempdir().expect("Could not create temp dir");
+
+		let cache = init_test_op_log_cache(dir.path());
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.shutdown().is_ok());
+		assert!(cache.shutdown().is_ok());
+
+		let restored = init_test_op_log_cache(dir.path());
+		assert_eq!(restored.get(&0), Ok(1.into()));
+	}
+
+	#[test]
+	fn it_shuts_down() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.shutdown().is_ok());
+		assert!(cache.shutdown().is_ok());
+
+		assert_eq!(cache.set(1, 1, None), Err(CacheError::ShutDown));
+		assert_eq!(cache.settle(), Err(CacheError::ShutDown));
+	}
+
+	#[test]
+	fn it_does_not_modify_a_shut_down_cache() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.shutdown().is_ok());
+
+		let status = cache.status().unwrap();
+
+		assert_eq!(cache.set(1, 1, None), Err(CacheError::ShutDown));
+		assert_eq!(cache.set(0, 2, None), Err(CacheError::ShutDown));
+		assert_eq!(cache.del(&0), Err(CacheError::ShutDown));
+		assert_eq!(cache.ttl(&0, Some(5)), Err(CacheError::ShutDown));
+		assert_eq!(cache.wipe(), Err(CacheError::ShutDown));
+		assert_eq!(cache.resize(1), Err(CacheError::ShutDown));
+
+		assert!(!cache.has(&1));
+		assert_eq!(cache.peek(&0), Ok(1.into()));
+		assert_eq!(cache.status().unwrap().used_size(), status.used_size());
+		assert_eq!(cache.status().unwrap().num_objects(), status.num_objects());
+	}
+
+	#[test]
+	fn it_gets_from_a_shut_down_cache() {
+		let cache = init_test_cache();
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.shutdown().is_ok());
+
+		assert_eq!(cache.get(&0), Ok(1.into()));
+		assert_eq!(cache.get(&1), Err(CacheError::KeyNotFound));
+	}
+
+	#[test]
+	fn it_applies_pending_events_on_shutdown() {
+		use std::sync::{Arc, Mutex};
+
+		let removals = Arc::new(Mutex::new(Vec::new()));
+		let listener_removals = removals.clone();
+
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.listener(move |key, _, cause| {
+			listener_removals.lock().unwrap().push((key, cause));
+		})
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 1, None).is_ok());
+		assert!(cache.resize(1).is_ok());
+		assert!(cache.shutdown().is_ok());
+
+		assert_eq!(removals.lock().unwrap().len(), 2);
+	}
+
+	#[test]
+	fn it_releases_the_workers_on_drop() {
+		use std::sync::Arc;
+
+		let token = Arc::new(());
+		let listener_token = token.clone();
+
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.listener(move |_, _, _| {
+			let _ = &listener_token;
+		})
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(Arc::strong_count(&token) > 1);
+
+		drop(cache);
+
+		// each of the workers held a reference to the listener
+		assert_eq!(Arc::strong_count(&token), 1);
+	}
+
+	#[test]
+	fn it_drops_gets_when_the_queue_is_full() {
+		use crate::Backpressure;
+
+		let (cache, release) = init_stalled_test_cache(Backpressure::Block);
+
+		for _ in 0..10 {
+			assert!(cache.get(&0).is_err());
+		}
+
+		// at most one get fits in each of the queues
+		assert!(cache.status().unwrap().dropped_gets() >= 8);
+
+		drop(release);
+	}
+
+	#[test]
+	fn it_fails_fast_when_the_queue_is_full() {
+		use crate::Backpressure;
+
+		let (cache, release) = init_stalled_test_cache(Backpressure::FailFast);
+
+		let results = (100..110)
+			.map(|key| cache.set(key, 1, None))
+			.collect::<Vec<_>>();
+
+		assert!(results.contains(&Err(CacheError::QueueFull)));
+
+		drop(release);
+	}
+
+	#[test]
+	fn it_fails_fast_or_applies_concurrent_sets() {
+		use std::thread;
+
+		use crate::Backpressure;
+
+		let (cache, release) = init_stalled_test_cache(Backpressure::FailFast);
+
+		let results = thread::scope(|scope| {
+			let handles = (100..116)
+				.map(|key| {
+					let cache = &cache;
+					scope.spawn(move || (key, cache.set(key, 1, None)))
+				})
+				.collect::<Vec<_>>();
+
+			// the sets which found room in the queue block until the workers
+			// are released rather than failing
+			while !cache.worker_manager.is_full() {
+				thread::yield_now();
+			}
+
+			drop(release);
+
+			handles
+				.into_iter()
+				.map(|handle| handle.join().expect("Could not join set thread"))
+				.collect::<Vec<_>>()
+		});
+
+		assert!(cache.settle().is_ok());
+
+		// the stalled cache only has room for a single object, so the sets
+		// which were applied may since have been evicted
+		for (key, result) in results {
+			if let Err(err) = result {
+				assert_eq!(err, CacheError::QueueFull);
+				assert!(!cache.has(&key));
+			}
+		}
+	}
+
+	#[test]
+	fn it_never_exceeds_the_max_size_in_strict_mode() {
+		let cache = init_strict_test_cache();
+
+		for key in 0..200 {
+			assert!(cache.set(key, key, Some(60)).is_ok());
+			assert!(cache.status().unwrap().used_size() <= TEST_CACHE_MAX_SIZE);
+		}
+
+		assert!(cache.has(&199));
+	}
+
+	#[test]
+	fn it_evicts_the_earlier_objects_of_an_mset_in_strict_mode() {
+		let cache = init_strict_test_cache();
+
+		let results = cache
+			.mset((0..200).map(|key| (key, key, None)))
+			.unwrap();
+
+		assert!(results.iter().all(|result| result.is_ok()));
+		assert!(cache.status().unwrap().used_size() <= TEST_CACHE_MAX_SIZE);
+		assert!(!cache.has(&0));
+
+		for key in 195..200 {
+			assert!(cache.has(&key));
+		}
+	}
+
+	#[test]
+	fn it_evicts_before_resizing_in_strict_mode() {
+		let cache = init_strict_test_cache();
+
+		for key in 0..50 {
+			assert!(cache.set(key, key, None).is_ok());
+		}
+
+		assert!(cache.resize(100).is_ok());
+		assert!(cache.status().unwrap().used_size() <= 100);
+	}
+
+	#[test]
+	fn it_does_not_evict_for_a_missing_ttl_key_in_strict_mode() {
+		let cache = init_strict_test_cache();
+
+		for key in 0..200 {
+			assert!(cache.set(key, key, None).is_ok());
+		}
+
+		assert!(cache.settle().is_ok());
+		let num_objects = cache.status().unwrap().num_objects();
+
+		assert_eq!(cache.ttl(&1000, Some(60)), Err(CacheError::KeyNotFound));
+
+		assert!(cache.settle().is_ok());
+		assert_eq!(cache.status().unwrap().num_objects(), num_objects);
+	}
+
+	#[test]
+	fn it_rejects_objects_above_the_size_threshold() {
+		use crate::SizeThreshold;
+
+		let cache = PaperCache::<u32, Vec<u8>>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.admission(SizeThreshold::new(200))
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, vec![0; 10], None).is_ok());
+		assert_eq!(cache.set(1, vec![0; 500], None), Err(CacheError::Rejected));
+		assert!(!cache.has(&1));
+
+		assert_eq!(cache.status().unwrap().rejected_sets(), 1);
+	}
+
+	#[test]
+	fn it_admits_frequently_accessed_objects_with_tiny_lfu() {
+		use std::hash::{BuildHasherDefault, DefaultHasher};
+
+		use crate::TinyLfu;
+
+		// the keys are hashed deterministically so that the sketch's
+		// collisions are the same in every run
+		let cache = PaperCache::<u32, u32, BuildHasherDefault<DefaultHasher>>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.admission(TinyLfu::new(1024))
+		.build()
+		.expect("Could not initialize test cache");
+
+		// the objects are admitted until the cache is full, and none are
+		// evicted since each admitted object fits in the available space
+		let mut num_objects = 0;
+
+		while cache.set(num_objects, 0, None).is_ok() {
+			num_objects += 1;
+		}
+
+		assert!(cache.settle().is_ok());
+
+		// the cache is full since the remaining space cannot fit another object
+		let status = cache.status().unwrap();
+		let object_size = u64::from(cache.size(&0).unwrap());
+
+		assert_eq!(status.num_objects(), u64::from(num_objects));
+		assert!(status.max_size() - status.used_size() < object_size);
+
+		// a key which was accessed more frequently than the last evicted key is
+		// admitted, while a key which was never accessed is rejected
+		assert!(cache.get(&1000).is_err());
+		assert!(cache.set(1000, 0, None).is_ok());
+		assert_eq!(cache.set(1001, 0, None), Err(CacheError::Rejected));
+
+		// updating an existing object is always admitted
+		assert!(cache.set(1000, 1, None).is_ok());
 	}
 
-	fn hash_key(&self, key: &K) -> HashedKey {
-		self.hasher.hash_one(key)
+	#[test]
+	fn it_evicts_the_nearest_expiry_after_switching_to_volatile_ttl() {
+		use std::{thread, time::Duration};
+
+		let cache = PaperCache::<u32, u32>::new(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lru, PaperPolicy::VolatileTtl],
+			PaperPolicy::Lru,
+		)
+		.expect("Could not initialize test cache");
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 1, Some(100)).is_ok());
+		assert!(cache.set(2, 1, Some(50)).is_ok());
+
+		// wait for the sets to be traced so that the stack is reconstructed
+		// with their expiries
+		thread::sleep(Duration::from_secs(2));
+
+		assert!(cache.policy(PaperPolicy::VolatileTtl).is_ok());
+		assert!(cache.settle().is_ok());
+
+		let object_size = cache.size(&0).unwrap();
+		assert!(cache.resize(object_size.into()).is_ok());
+		assert!(cache.settle().is_ok());
+
+		assert!(cache.has(&0));
+		assert!(!cache.has(&1));
+		assert!(!cache.has(&2));
 	}
-}
 
-pub enum EraseKey<'a, K> {
-	Original(&'a K, HashedKey),
-	Hashed(HashedKey),
-}
+	#[test]
+	fn it_evicts_the_lowest_cost_after_switching_to_gdsf() {
+		use std::{thread, time::Duration};
 
-pub fn erase<K, V>(
-	objects: &ObjectMapRef<K, V>,
-	status: &StatusRef,
-	overhead_manager: &OverheadManagerRef,
-	maybe_key: Option<EraseKey<K>>,
-) -> Result<(HashedKey, Object<K, V>), CacheError>
-where
-	K: Eq + TypeSize,
-	V: TypeSize,
-{
-	let hashed_key = match maybe_key {
-		Some(EraseKey::Original(_, hashed_key)) => hashed_key,
-		Some(EraseKey::Hashed(hashed_key)) => hashed_key,
+		let cache = PaperCache::<u32, u32>::new(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lru, PaperPolicy::Gdsf],
+			PaperPolicy::Lru,
+		)
+		.expect("Could not initialize test cache");
 
-		None => {
-			// the policy has run out of keys to evict (either it's a mini stack or
-			// something went wrong during policy reconstruction) so we fall back
-			// to evicting a random object
+		assert!(cache.set_with_options(0, 1, SetOptions::new().cost(100)).is_ok());
+		assert!(cache.set_with_options(1, 1, SetOptions::new().cost(1)).is_ok());
+		assert!(cache.set_with_options(2, 1, SetOptions::new().cost(10)).is_ok());
 
-			let Some(object) = objects.iter().next() else {
-				error!("Object store is empty with non-zero used size");
-				return Err(CacheError::Internal);
-			};
+		// wait for the sets to be traced so that the stack is reconstructed
+		// with their costs
+		thread::sleep(Duration::from_secs(2));
 
-			object.key().to_owned()
-		},
-	};
+		assert!(cache.policy(PaperPolicy::Gdsf).is_ok());
+		assert!(cache.settle().is_ok());
 
-	// don't remove the object right away because if we have the original key,
-	// we need to do a validation check that it matches the object's key in
-	// case of a hash collision
-	let Entry::Occupied(entry) = objects.entry(hashed_key) else {
-		return Err(CacheError::KeyNotFound);
-	};
+		let object_size = cache.size(&0).unwrap();
+		assert!(cache.resize(object_size.into()).is_ok());
+		assert!(cache.settle().is_ok());
 
-	if let Some(EraseKey::Original(key, _)) = maybe_key
-		&& !entry.get().key_matches(key)
-	{
-		return Err(CacheError::KeyNotFound);
-	};
+		assert!(cache.has(&0));
+		assert!(!cache.has(&1));
+		assert!(!cache.has(&2));
+	}
 
-	let object = entry.remove();
-	let base_size = overhead_manager.base_size(&object) as i64;
+	#[test]
+	fn it_switches_to_the_optimal_policy_with_auto() {
+		let cache = init_auto_policy_test_cache(AutoPolicyConfig::new());
 
-	status.update_base_used_size(-base_size);
-	status.decr_num_objects();
+		run_recency_workload(&cache);
+		assert_eq!(cache.status().unwrap().policy(), PaperPolicy::Lru);
+	}
 
-	match !object.is_expired() {
-		true => Ok((hashed_key, object)),
-		false => Err(CacheError::KeyNotFound),
+	#[test]
+	fn it_does_not_switch_policy_below_the_min_improvement() {
+		let cache = init_auto_policy_test_cache(AutoPolicyConfig::new().min_improvement(1.0));
+
+		run_recency_workload(&cache);
+		assert_eq!(cache.status().unwrap().policy(), PaperPolicy::Lfu);
 	}
-}
 
-unsafe impl<K, V, S> Send for PaperCache<K, V, S> {}
+	#[test]
+	fn it_reports_the_policies_miss_ratios() {
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu, PaperPolicy::Lru],
+			PaperPolicy::Lfu,
+		)
+		.auto_policy(AutoPolicyConfig::new().sample_rate(1.0))
+		.build()
+		.expect("Could not initialize test cache");
 
-#[cfg(test)]
-mod tests {
-	use crate::{CacheError, PaperCache, PaperPolicy};
+		run_recency_workload(&cache);
 
-	const TEST_CACHE_MAX_SIZE: u64 = 1000;
+		let report = cache.policy_report().expect("Could not get policy report");
+		assert_eq!(report.num_samples(), 700);
+
+		let lfu = report.estimate(PaperPolicy::Lfu).unwrap();
+		let lru = report.estimate(PaperPolicy::Lru).unwrap();
+
+		assert!(lru.miss_ratio() < lfu.miss_ratio());
+		assert!(lru.byte_miss_ratio() < lfu.byte_miss_ratio());
+
+		for estimate in report.estimates() {
+			let (lower, upper) = estimate.miss_ratio_interval();
+			assert!(lower <= estimate.miss_ratio() && estimate.miss_ratio() <= upper);
+		}
+
+		// the report never switches the policy
+		assert_eq!(cache.status().unwrap().policy(), PaperPolicy::Lfu);
+	}
 
 	#[test]
-	fn it_returns_correct_version() {
-		let cache = init_test_cache();
-		assert_eq!(cache.version(), env!("CARGO_PKG_VERSION"));
+	fn it_records_the_auto_policy_history() {
+		let cache = init_auto_policy_test_cache(AutoPolicyConfig::new().min_improvement(1.0));
+
+		run_recency_workload(&cache);
+
+		let history = cache.auto_policy_history();
+		assert!(!history.is_empty());
+
+		let decision = history
+			.iter()
+			.find(|decision| decision.reason() == AutoPolicyReason::BelowMinImprovement)
+			.expect("Could not find decision");
+
+		assert!(decision.num_samples() > 0);
+		assert_eq!(decision.current_policy(), PaperPolicy::Lfu);
+		assert_eq!(decision.chosen_policy(), PaperPolicy::Lru);
+		assert_eq!(decision.miss_ratios().len(), 2);
+
+		assert_eq!(cache.status().unwrap().auto_policy_history().len(), history.len());
 	}
 
 	#[test]
-	fn it_returns_status() {
-		let cache = init_test_cache();
-		let status = cache.status().unwrap();
+	fn it_reconfigures_the_auto_policy() {
+		let cache = init_auto_policy_test_cache(AutoPolicyConfig::new().min_improvement(1.0));
 
-		assert_eq!(status.max_size(), TEST_CACHE_MAX_SIZE);
+		let config = AutoPolicyConfig::new()
+			.epoch(Duration::ZERO)
+			.sample_rate(1.0);
+
+		assert!(cache.configure_auto_policy(config).is_ok());
+
+		run_recency_workload(&cache);
+		assert_eq!(cache.status().unwrap().policy(), PaperPolicy::Lru);
 	}
 
 	#[test]
-	fn it_gets_an_existing_object() {
-		let cache = init_test_cache();
+	fn it_rejects_sets_when_full_with_no_eviction() {
+		let cache = PaperCache::<u32, u32>::new(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::NoEviction],
+			PaperPolicy::NoEviction,
+		)
+		.expect("Could not initialize test cache");
 
-		assert!(cache.set(0, 1, None).is_ok());
-		assert_eq!(cache.get(&0).as_deref(), Ok(&1));
+		let mut num_sets = 0;
+
+		while cache.set(num_sets, 1, None).is_ok() {
+			num_sets += 1;
+		}
+
+		assert_eq!(cache.set(num_sets, 1, None), Err(CacheError::CacheFull));
+
+		// replacing an existing object reuses its space
+		assert!(cache.set(0, 2, None).is_ok());
+		assert!(cache.settle().is_ok());
+
+		for key in 0..num_sets {
+			assert!(cache.has(&key));
+		}
 	}
 
 	#[test]
-	fn it_does_not_get_a_non_existing_object() {
-		let cache = init_test_cache();
+	fn it_rejects_switching_to_no_eviction_when_over_capacity() {
+		use crossbeam_channel::unbounded;
 
-		assert!(cache.set(0, 1, None).is_ok());
-		assert_eq!(cache.get(&1), Err(CacheError::KeyNotFound));
+		let (entered_tx, entered_rx) = unbounded::<()>();
+		let (release_tx, release_rx) = unbounded::<()>();
+
+		// the listener stalls the first eviction so that the cache stays over
+		// its maximum size
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lru, PaperPolicy::NoEviction],
+			PaperPolicy::Lru,
+		)
+		.listener(move |_, _, _| {
+			entered_tx.send(()).ok();
+			release_rx.recv().ok();
+		})
+		.build()
+		.expect("Could not initialize test cache");
+
+		for key in 0..200 {
+			assert!(cache.set(key, key, None).is_ok());
+		}
+
+		assert!(entered_rx.recv().is_ok());
+		assert_eq!(cache.policy(PaperPolicy::NoEviction), Err(CacheError::CacheFull));
+
+		drop(release_tx);
+		assert!(cache.settle().is_ok());
+
+		assert!(cache.policy(PaperPolicy::NoEviction).is_ok());
+		assert!(cache.settle().is_ok());
+
+		let status = cache.status().unwrap();
+		assert_eq!(status.policy(), PaperPolicy::NoEviction);
+		assert!(status.used_size() <= TEST_CACHE_MAX_SIZE);
 	}
 
 	#[test]
-	fn it_calculates_miss_ratio_correctly() {
-		let cache = init_test_cache();
+	fn it_never_exceeds_the_max_size_with_no_eviction() {
+		use std::{sync::Arc, thread};
+
+		let cache = Arc::new(
+			PaperCache::<u32, u32>::new(
+				TEST_CACHE_MAX_SIZE,
+				&[PaperPolicy::NoEviction],
+				PaperPolicy::NoEviction,
+			)
+			.expect("Could not initialize test cache"),
+		);
 
-		assert!(cache.set(0, 1, None).is_ok());
-		assert!(cache.get(&0).is_ok());
-		assert!(cache.get(&0).is_ok());
-		assert!(cache.get(&0).is_ok());
-		assert!(cache.get(&1).is_err());
+		let handles = (0..4)
+			.map(|thread_index| {
+				let cache = cache.clone();
+
+				thread::spawn(move || {
+					for key in 0..100 {
+						cache.set(thread_index * 100 + key, key, None).ok();
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for handle in handles {
+			handle.join().expect("Could not join test thread");
+		}
 
 		let status = cache.status().unwrap();
-		assert_eq!(status.miss_ratio(), 0.25);
+		assert!(status.used_size() <= TEST_CACHE_MAX_SIZE);
+
+		// the cache cannot shrink below the size of its objects
+		assert_eq!(cache.resize(1), Err(CacheError::CacheFull));
 	}
 
 	#[test]
@@ -803,6 +2690,95 @@ mod tests {
 		assert!(try_cache.is_ok());
 	}
 
+	#[test]
+	fn it_does_not_allow_unregistered_custom_policies() {
+		let policy = PaperPolicy::Custom("unregistered");
+		let try_cache = PaperCache::<u32, u32>::new(TEST_CACHE_MAX_SIZE, &[policy], policy);
+
+		assert!(try_cache.is_err_and(|err| err == CacheError::UnregisteredPolicy));
+
+		// custom policies cannot replace the built-in policies
+		let policy = PaperPolicy::Custom("lru");
+
+		let try_cache = PaperCache::<u32, u32>::builder(TEST_CACHE_MAX_SIZE, &[policy], policy)
+			.custom_policy("lru", 8, |_| Box::new(NewestStack::default()))
+			.build();
+
+		assert!(try_cache.is_err_and(|err| err == CacheError::InvalidPolicy));
+
+		let policy = PaperPolicy::Custom("duplicate");
+
+		let try_cache = PaperCache::<u32, u32>::builder(TEST_CACHE_MAX_SIZE, &[policy], policy)
+			.custom_policy("duplicate", 8, |_| Box::new(NewestStack::default()))
+			.custom_policy("duplicate", 8, |_| Box::new(NewestStack::default()))
+			.build();
+
+		assert!(try_cache.is_err_and(|err| err == CacheError::DuplicatePolicies));
+	}
+
+	#[test]
+	fn it_does_not_share_custom_policies_between_caches() {
+		let policy = PaperPolicy::Custom("shared");
+
+		let cache = PaperCache::<u32, u32>::builder(TEST_CACHE_MAX_SIZE, &[policy], policy)
+			.custom_policy("shared", 8, |_| Box::new(NewestStack::default()))
+			.build()
+			.expect("Could not initialize test cache");
+
+		assert_eq!(cache.parse_policy("shared"), Ok(policy));
+
+		let try_cache = PaperCache::<u32, u32>::new(TEST_CACHE_MAX_SIZE, &[policy], policy);
+		assert!(try_cache.is_err_and(|err| err == CacheError::UnregisteredPolicy));
+
+		let other_cache = init_test_cache();
+
+		assert_eq!(other_cache.parse_policy("shared"), Err(CacheError::InvalidPolicy));
+		assert_eq!("shared".parse::<PaperPolicy>(), Err(CacheError::InvalidPolicy));
+	}
+
+	#[test]
+	fn it_allows_custom_policies_with_a_built_in_prefix() {
+		let policy = PaperPolicy::Custom("lru-newest");
+
+		let cache = PaperCache::<u32, u32>::builder(TEST_CACHE_MAX_SIZE, &[policy], policy)
+			.custom_policy("lru-newest", 8, |_| Box::new(NewestStack::default()))
+			.build();
+
+		let cache = cache.expect("Could not initialize test cache");
+
+		assert_eq!(cache.parse_policy("lru-newest"), Ok(policy));
+		assert_eq!(cache.parse_policy("lru-2"), Ok(PaperPolicy::LruK(2)));
+	}
+
+	#[test]
+	fn it_evicts_with_a_custom_policy() {
+		let policy = PaperPolicy::Custom("newest");
+
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lru, policy],
+			policy,
+		)
+		.custom_policy("newest", 8, |_| Box::new(NewestStack::default()))
+		.build()
+		.expect("Could not initialize test cache");
+
+		assert_eq!(cache.parse_policy("newest"), Ok(policy));
+
+		assert!(cache.set(0, 1, None).is_ok());
+		assert!(cache.set(1, 1, None).is_ok());
+		assert!(cache.set(2, 1, None).is_ok());
+		assert!(cache.settle().is_ok());
+
+		let object_size = cache.size(&0).unwrap();
+		assert!(cache.resize(object_size.into()).is_ok());
+		assert!(cache.settle().is_ok());
+
+		assert!(cache.has(&0));
+		assert!(!cache.has(&1));
+		assert!(!cache.has(&2));
+	}
+
 	#[test]
 	fn it_has_an_existing_object() {
 		let cache = init_test_cache();
@@ -1083,8 +3059,145 @@ mod tests {
 		assert_eq!(status.used_size(), post_expected as u64);
 	}
 
+	/// A custom policy which evicts the most recently inserted key.
+	#[derive(Default)]
+	struct NewestStack {
+		keys: Vec<HashedKey>,
+	}
+
+	impl PolicyStack for NewestStack {
+		fn is_policy(&self, policy: &PaperPolicy) -> bool {
+			*policy == PaperPolicy::Custom("newest")
+		}
+
+		fn len(&self) -> usize {
+			self.keys.len()
+		}
+
+		fn contains(&self, key: HashedKey) -> bool {
+			self.keys.contains(&key)
+		}
+
+		fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
+			if !self.contains(key) {
+				self.keys.push(key);
+			}
+		}
+
+		fn remove(&mut self, key: HashedKey) {
+			self.keys.retain(|got_key| *got_key != key);
+		}
+
+		fn clear(&mut self) {
+			self.keys.clear();
+		}
+
+		fn evict_one(&mut self) -> Option<HashedKey> {
+			self.keys.pop()
+		}
+	}
+
 	fn init_test_cache() -> PaperCache<u32, u32> {
 		PaperCache::<u32, u32>::new(TEST_CACHE_MAX_SIZE, &[PaperPolicy::Lfu], PaperPolicy::Lfu)
 			.expect("Could not initialize test cache")
 	}
+
+	fn init_strict_test_cache() -> PaperCache<u32, u32> {
+		PaperCache::<u32, u32>::builder(TEST_CACHE_MAX_SIZE, &[PaperPolicy::Lfu], PaperPolicy::Lfu)
+			.strict()
+			.build()
+			.expect("Could not initialize test cache")
+	}
+
+	fn init_auto_policy_test_cache(config: AutoPolicyConfig) -> PaperCache<u32, u32> {
+		// every key is sampled and the policies are compared whenever the
+		// cache is idle
+		let config = config
+			.epoch(Duration::ZERO)
+			.sample_rate(1.0);
+
+		PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu, PaperPolicy::Lru],
+			PaperPolicy::Auto,
+		)
+		.auto_policy(config)
+		.build()
+		.expect("Could not initialize test cache")
+	}
+
+	// runs a workload whose frequently accessed keys are never accessed
+	// again, so LRU performs better than LFU
+	fn run_recency_workload(cache: &PaperCache<u32, u32>) {
+		for key in 0..40 {
+			assert!(cache.set(key, key, None).is_ok());
+
+			for _ in 0..10 {
+				cache.get(&key).ok();
+			}
+		}
+
+		for key in 100..200 {
+			assert!(cache.set(key, key, None).is_ok());
+			cache.get(&key).ok();
+		}
+
+		// the policies are only compared once there are no sets
+		assert!(cache.settle().is_ok());
+
+		for _ in 0..5 {
+			cache.mget(&(160..200).collect::<Vec<_>>()).ok();
+		}
+
+		// the first settle may complete before the policies are compared,
+		// while the second waits for any policy switch
+		assert!(cache.settle().is_ok());
+		assert!(cache.settle().is_ok());
+	}
+
+	/// Returns a cache whose policy worker is blocked in the removal listener
+	/// until the returned sender is dropped.
+	fn init_stalled_test_cache(
+		backpressure: crate::Backpressure,
+	) -> (PaperCache<u32, u32>, crossbeam_channel::Sender<()>) {
+		use crossbeam_channel::unbounded;
+
+		let (entered_tx, entered_rx) = unbounded::<()>();
+		let (release_tx, release_rx) = unbounded::<()>();
+
+		let cache = PaperCache::<u32, u32>::builder(
+			TEST_CACHE_MAX_SIZE,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+		)
+		.channel_capacity(1)
+		.backpressure(backpressure)
+		.listener(move |_, _, _| {
+			entered_tx.send(()).ok();
+			release_rx.recv().ok();
+		})
+		.build()
+		.expect("Could not initialize test cache");
+
+		// settling after each set drains the queues so that none of the sets
+		// fail fast
+		for key in 0..5 {
+			assert!(cache.set(key, 1, None).is_ok());
+			assert!(cache.settle().is_ok());
+		}
+
+		assert!(cache.resize(100).is_ok());
+		assert!(entered_rx.recv().is_ok());
+
+		(cache, release_tx)
+	}
+
+	fn init_test_op_log_cache(dir: &std::path::Path) -> PaperCache<u32, u32> {
+		use crate::FsyncPolicy;
+
+		PaperCache::<u32, u32>::builder(TEST_CACHE_MAX_SIZE, &[PaperPolicy::Lfu], PaperPolicy::Lfu)
+			.op_log(dir, FsyncPolicy::Always)
+			.build()
+			.expect("Could not initialize test cache")
+	}
 }
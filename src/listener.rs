@@ -0,0 +1,48 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use crate::{RemovalListenerRef, object::Object};
+
+/// The reason an object was removed from the cache.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RemovalCause {
+	/// The object was evicted by the eviction policy.
+	Evicted,
+
+	/// The object's TTL expired.
+	Expired,
+
+	/// The object was deleted.
+	Deleted,
+
+	/// The object's value was replaced by a set.
+	Replaced,
+
+	/// The object was removed by a wipe.
+	Wiped,
+}
+
+/// Invokes the removal listener (if one is registered) with the supplied
+/// removed object. Objects which have already expired are always reported
+/// as expired regardless of the supplied cause.
+pub fn notify_removal<K, V>(
+	listener: &RemovalListenerRef<K, V>,
+	object: Object<K, V>,
+	cause: RemovalCause,
+) {
+	let Some(listener) = listener else {
+		return;
+	};
+
+	let cause = match object.is_expired() {
+		true => RemovalCause::Expired,
+		false => cause,
+	};
+
+	let (key, data) = object.into_parts();
+	listener(key, data, cause);
+}
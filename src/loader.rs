@@ -0,0 +1,144 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::sync::Arc;
+
+use dashmap::{DashMap, mapref::entry::Entry};
+use parking_lot::{Condvar, Mutex};
+#[cfg(feature = "async")]
+use tokio::sync::watch;
+
+use crate::{HashedKey, NoHasher};
+
+/// Tracks the in-flight loads so that concurrent misses of the same key
+/// only run a single loader.
+#[derive(Default)]
+pub struct Loaders {
+	in_flight: DashMap<HashedKey, Arc<Load>, NoHasher>,
+}
+
+#[derive(Default)]
+struct Load {
+	is_complete: Mutex<bool>,
+	condvar:     Condvar,
+}
+
+pub struct LoadGuard<'a> {
+	loaders: &'a Loaders,
+	key:     HashedKey,
+	load:    Arc<Load>,
+}
+
+impl Loaders {
+	/// Returns a guard if the caller is now responsible for loading the value
+	/// associated with the key. Otherwise, waits for the in-flight load to
+	/// complete and returns `None`.
+	pub fn join(&self, key: HashedKey) -> Option<LoadGuard<'_>> {
+		let load = match self.in_flight.entry(key) {
+			Entry::Occupied(entry) => entry.get().clone(),
+
+			Entry::Vacant(entry) => {
+				let load = Arc::new(Load::default());
+				entry.insert(load.clone());
+
+				let guard = LoadGuard {
+					loaders: self,
+					key,
+					load,
+				};
+
+				return Some(guard);
+			},
+		};
+
+		load.wait();
+
+		None
+	}
+}
+
+impl Load {
+	fn wait(&self) {
+		let mut is_complete = self.is_complete.lock();
+
+		while !*is_complete {
+			self.condvar.wait(&mut is_complete);
+		}
+	}
+
+	fn complete(&self) {
+		*self.is_complete.lock() = true;
+		self.condvar.notify_all();
+	}
+}
+
+impl Drop for LoadGuard<'_> {
+	fn drop(&mut self) {
+		// the load is removed before waking the waiting callers so that any of
+		// them can become responsible for the next load if this one failed
+		self.loaders.in_flight.remove(&self.key);
+		self.load.complete();
+	}
+}
+
+/// Tracks the in-flight asynchronous loads so that concurrent misses of the
+/// same key only await a single loader.
+#[cfg(feature = "async")]
+#[derive(Default)]
+pub struct AsyncLoaders {
+	in_flight: DashMap<HashedKey, watch::Receiver<()>, NoHasher>,
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncLoadGuard<'a> {
+	loaders: &'a AsyncLoaders,
+	key:     HashedKey,
+
+	// the waiting callers are woken when the sender is dropped
+	_sender: watch::Sender<()>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncLoaders {
+	/// Returns a guard if the caller is now responsible for loading the value
+	/// associated with the key. Otherwise, waits for the in-flight load to
+	/// complete and returns `None`.
+	pub async fn join(&self, key: HashedKey) -> Option<AsyncLoadGuard<'_>> {
+		let mut receiver = match self.in_flight.entry(key) {
+			Entry::Occupied(entry) => entry.get().clone(),
+
+			Entry::Vacant(entry) => {
+				let (sender, receiver) = watch::channel(());
+				entry.insert(receiver);
+
+				let guard = AsyncLoadGuard {
+					loaders: self,
+					key,
+
+					_sender: sender,
+				};
+
+				return Some(guard);
+			},
+		};
+
+		// nothing is ever sent, so this only returns once the sender is dropped
+		while receiver.changed().await.is_ok() {}
+
+		None
+	}
+}
+
+#[cfg(feature = "async")]
+impl Drop for AsyncLoadGuard<'_> {
+	fn drop(&mut self) {
+		// the sender is dropped (waking the waiting callers) after the load
+		// is removed so that any of them can become responsible for the next
+		// load if this one failed or was cancelled
+		self.loaders.in_flight.remove(&self.key);
+	}
+}
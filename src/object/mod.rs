@@ -15,9 +15,26 @@ use std::{
 
 use typesize::TypeSize;
 
+use crate::{CacheError, HashedKey, ObjectMapRef};
+
 pub type ObjectSize = u32;
+pub type ObjectCost = u32;
 pub type ExpireTime = Option<Instant>;
 
+/// Hints about an object which are supplied with
+/// [`PaperCache::set_with_options`](crate::PaperCache::set_with_options)
+/// and passed to cost-aware eviction policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectHints {
+	/// The cost of a miss on the object (e.g., the number of milliseconds it
+	/// takes to recompute the value). Defaults to 1.
+	pub cost: ObjectCost,
+
+	/// The object's priority class. Cost-aware policies evict objects in
+	/// lower priority classes before objects in higher ones. Defaults to 0.
+	pub priority: u8,
+}
+
 pub struct Object<K, V> {
 	key:  K,
 	data: Arc<V>,
@@ -40,10 +57,18 @@ impl<K, V> Object<K, V> {
 		}
 	}
 
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
 	pub fn data(&self) -> Arc<V> {
 		self.data.clone()
 	}
 
+	pub fn into_parts(self) -> (K, Arc<V>) {
+		(self.key, self.data)
+	}
+
 	pub fn key_matches(&self, key: &K) -> bool
 	where
 		K: Eq,
@@ -63,6 +88,14 @@ impl<K, V> Object<K, V> {
 		self.expiry
 	}
 
+	/// Returns the object's remaining TTL in seconds (rounded up), or `None`
+	/// if the object does not expire. The TTL is at least one second since a
+	/// TTL of zero means the object does not expire.
+	pub fn remaining_ttl(&self) -> Option<u32> {
+		let remaining = self.expiry?.saturating_duration_since(Instant::now());
+		Some((remaining.as_millis().div_ceil(1000) as u32).max(1))
+	}
+
 	pub fn is_expired(&self) -> bool {
 		self.expiry
 			.is_some_and(|expiry| expiry <= Instant::now())
@@ -76,6 +109,47 @@ impl<K, V> Object<K, V> {
 	}
 }
 
+impl Default for ObjectHints {
+	fn default() -> Self {
+		ObjectHints {
+			cost:     1,
+			priority: 0,
+		}
+	}
+}
+
 pub fn get_expiry_from_ttl(ttl: u32) -> Instant {
 	Instant::now() + Duration::from_secs(ttl.into())
 }
+
+/// Serializes the live object of each of the supplied keys into a buffer and
+/// writes the buffer. The object is serialized while the map's guard is held,
+/// but written only once the guard is dropped so that slow I/O does not block
+/// operations on the object's shard.
+pub fn write_live_objects<K, V>(
+	objects: &ObjectMapRef<K, V>,
+	hashed_keys: impl IntoIterator<Item = HashedKey>,
+	mut serialize: impl FnMut(&mut Vec<u8>, &Object<K, V>) -> Result<(), CacheError>,
+	mut write: impl FnMut(&[u8]) -> Result<(), CacheError>,
+) -> Result<(), CacheError> {
+	let mut buffer = Vec::new();
+
+	for hashed_key in hashed_keys {
+		{
+			let Some(object) = objects.get(&hashed_key) else {
+				continue;
+			};
+
+			if object.is_expired() {
+				continue;
+			}
+
+			buffer.clear();
+			serialize(&mut buffer, &object)?;
+		}
+
+		write(&buffer)?;
+	}
+
+	Ok(())
+}
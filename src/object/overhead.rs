@@ -49,7 +49,7 @@ impl OverheadManager {
 		V: TypeSize,
 	{
 		let policy = self.status.policy();
-		self.base_size(object) + get_policy_overhead(&policy)
+		self.base_size(object) + self.status.policy_overhead(&policy)
 	}
 }
 
@@ -92,6 +92,53 @@ pub fn get_policy_overhead(policy: &PaperPolicy) -> ObjectSize {
 		// 48 bytes for the HashList entry, 8 bytes for the HashedKey,
 		// 4 bytes for the object size, 1 byte for the frequency count
 		PaperPolicy::SThreeFifo(_) => 48 + 8 + 4 + 1,
+
+		// 48 bytes for the HashList entry, 8 bytes for the HashedKey,
+		// 4 bytes for the object size, 4 bytes for the frequency sketch
+		PaperPolicy::WTinyLfu(_) => 48 + 8 + 4 + 4,
+
+		// 48 bytes for the HashList entry, 8 bytes for the HashedKey,
+		// 4 bytes for the object size, 1 byte for the status
+		PaperPolicy::Lirs(_) => 48 + 8 + 4 + 1,
+
+		// 24 bytes for the HashMap entry, 32 bytes for the history,
+		// 8 bytes per access in the history, 24 bytes for the BTreeSet
+		// entry, 8 bytes for the HashedKey
+		PaperPolicy::LruK(k) => (24 + 32 + 24 + 8_u32).saturating_add(k.saturating_mul(8)),
+
+		// 8 bytes for the HashedKey, 4 bytes for the object size, 4 bytes
+		// for the cost, 1 byte for the priority class, 4 bytes for the
+		// frequency, 24 bytes for the priority in the HashMap and the
+		// BTreeSet each, 8 bytes for the HashedKey in the BTreeSet
+		PaperPolicy::Gdsf => 8 + 4 + 4 + 1 + 4 + 24 + 24 + 8,
+
+		// 8 bytes for the HashedKey and 8 bytes for the index in the HashMap,
+		// 8 bytes for the HashedKey and 4 bytes for the metadata in the Vec
+		PaperPolicy::SampledLru(_) | PaperPolicy::SampledLfu(_) | PaperPolicy::Random => 8 + 8 + 8 + 4,
+
+		// 8 bytes for the HashedKey, 4 bytes for the count, 16 bytes for the
+		// priority in the HashMap and the BTreeSet each, 8 bytes for the
+		// HashedKey in the BTreeSet
+		PaperPolicy::LfuDa => 8 + 4 + 16 + 16 + 8,
+
+		// 48 bytes for the HashList entry, 8 bytes for the HashedKey,
+		// 4 bytes for the object size
+		PaperPolicy::Slru(_) => 48 + 8 + 4,
+
+		// 48 bytes for the HashList entry, 8 bytes for the HashedKey,
+		// 1 byte for the count
+		PaperPolicy::MultiClock(_) => 48 + 8 + 1,
+
+		// 48 bytes for either the HashList entry or the HashMap and BTreeSet
+		// entries (including the expiry), 8 bytes for the HashedKey
+		PaperPolicy::VolatileTtl => 48 + 8,
+
+		// 8 bytes for the HashedKey in the HashSet
+		PaperPolicy::NoEviction => 8,
+
+		// the overhead of a custom policy is supplied when it is registered
+		// with a cache (see CustomPolicies::policy_overhead)
+		PaperPolicy::Custom(_) => 0,
 	}
 }
 
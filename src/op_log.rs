@@ -0,0 +1,632 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	fs::{self, File},
+	hash::{BuildHasher, Hash},
+	io::{self, BufReader, BufWriter, Write},
+	path::{Path, PathBuf},
+	sync::{
+		Arc,
+		Weak,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use log::{error, info};
+use parking_lot::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use typesize::TypeSize;
+
+use crate::{
+	CacheError,
+	HashedKey,
+	ObjectMapRef,
+	PaperCache,
+	object::{ExpireTime, write_live_objects},
+	status::StatusCounters,
+	worker::{ShutdownReceiver, Worker, WorkerHandle, register_worker, sleep_or_shutdown},
+};
+
+// once the active segment reaches this size, it is rotated and the older
+// segments are compacted into a base file
+const COMPACTION_SIZE: u64 = 64 * 1024 * 1024;
+const POLL_DELAY: Duration = Duration::from_secs(1);
+
+// the keys' operations are serialized by a fixed number of striped locks
+const NUM_KEY_LOCKS: usize = 64;
+
+const SEGMENT_EXTENSION: &str = "log";
+const BASE_EXTENSION: &str = "base";
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Configures when the operation log is synced to disk.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FsyncPolicy {
+	/// Sync after every logged operation.
+	Always,
+
+	/// Sync once per second in the background.
+	EverySecond,
+
+	/// Never explicitly sync, leaving it to the operating system.
+	Never,
+}
+
+pub type OpLogGuard<'a> = (RwLockReadGuard<'a, ()>, MutexGuard<'a, ()>);
+
+/// Records the operations which modify the cache's objects so that they can
+/// be replayed after a restart.
+pub trait OpLog<K, V>: Send + Sync {
+	/// Returns a guard which must be held while an operation on the supplied
+	/// key is both logged and applied to the cache's objects, so that the
+	/// key's operations are logged in the order in which they are applied
+	/// and the log is not rotated while the operation is in flight.
+	fn lock(&self, key: HashedKey) -> OpLogGuard<'_>;
+
+	/// Returns a guard which must be held while an operation on every key
+	/// (i.e., a wipe) is both logged and applied.
+	fn lock_all(&self) -> RwLockWriteGuard<'_, ()>;
+
+	fn set(&self, key: &K, value: &V, expiry: ExpireTime) -> Result<(), CacheError>;
+	fn del(&self, key: &K) -> Result<(), CacheError>;
+	fn ttl(&self, key: &K, expiry: ExpireTime) -> Result<(), CacheError>;
+	fn wipe(&self) -> Result<(), CacheError>;
+
+	/// Rotates the active segment and writes the current objects to a base
+	/// which supersedes all the older segments.
+	fn compact(&self) -> Result<(), CacheError>;
+
+	/// Stops the log's background worker and syncs the log to disk.
+	fn shutdown(&self) -> Result<(), CacheError>;
+}
+
+// expiries are logged as milliseconds since the unix epoch so that they
+// remain meaningful across restarts
+#[derive(Serialize, Deserialize)]
+enum LogRecord<K, V> {
+	Set(K, V, Option<u64>),
+	Del(K),
+	Ttl(K, Option<u64>),
+	Wipe,
+}
+
+pub struct FileOpLog<K, V> {
+	dir:   PathBuf,
+	fsync: FsyncPolicy,
+
+	segment: Mutex<Segment>,
+	objects: ObjectMapRef<K, V>,
+
+	key_locks:     Box<[Mutex<()>]>,
+	rotation_lock: RwLock<()>,
+	is_compacting: AtomicBool,
+
+	worker_handle: Mutex<Option<WorkerHandle>>,
+}
+
+// periodically syncs the log and compacts it once the active segment is
+// large enough
+struct OpLogWorker<K, V> {
+	op_log: Weak<FileOpLog<K, V>>,
+}
+
+struct Segment {
+	seq:    u64,
+	size:   u64,
+	writer: BufWriter<File>,
+}
+
+impl<K, V> OpLog<K, V> for FileOpLog<K, V>
+where
+	K: 'static + Eq + Hash + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+	V: 'static + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+{
+	fn lock(&self, key: HashedKey) -> OpLogGuard<'_> {
+		let key_lock = &self.key_locks[key as usize % NUM_KEY_LOCKS];
+		(self.rotation_lock.read(), key_lock.lock())
+	}
+
+	fn lock_all(&self) -> RwLockWriteGuard<'_, ()> {
+		self.rotation_lock.write()
+	}
+
+	fn set(&self, key: &K, value: &V, expiry: ExpireTime) -> Result<(), CacheError> {
+		self.append(&LogRecord::Set(key, value, expiry.map(to_unix_millis)))
+	}
+
+	fn del(&self, key: &K) -> Result<(), CacheError> {
+		self.append(&LogRecord::<_, &V>::Del(key))
+	}
+
+	fn ttl(&self, key: &K, expiry: ExpireTime) -> Result<(), CacheError> {
+		self.append(&LogRecord::<_, &V>::Ttl(key, expiry.map(to_unix_millis)))
+	}
+
+	fn wipe(&self) -> Result<(), CacheError> {
+		self.append(&LogRecord::<&K, &V>::Wipe)
+	}
+
+	fn compact(&self) -> Result<(), CacheError> {
+		if self.is_compacting.swap(true, Ordering::AcqRel) {
+			return Ok(());
+		}
+
+		let result = self.rotate().and_then(|seq| self.write_base(seq));
+		self.is_compacting.store(false, Ordering::Release);
+
+		result
+	}
+
+	fn shutdown(&self) -> Result<(), CacheError> {
+		if let Some(worker_handle) = self.worker_handle.lock().take() {
+			worker_handle.shutdown()?;
+		}
+
+		self.segment.lock().sync()
+	}
+}
+
+impl<K, V> FileOpLog<K, V>
+where
+	K: 'static + Eq + Hash + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+	V: 'static + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+{
+	/// Replays the operation log in the supplied directory into the cache and
+	/// opens a new segment to which subsequent operations are appended.
+	pub fn open<S>(
+		dir: &Path,
+		fsync: FsyncPolicy,
+		cache: &PaperCache<K, V, S>,
+	) -> Result<Arc<Self>, CacheError>
+	where
+		S: Default + Clone + BuildHasher,
+	{
+		if let Err(err) = fs::create_dir_all(dir) {
+			error!("Could not create operation log directory: {err:?}");
+			return Err(CacheError::OpLogWrite);
+		}
+
+		let files = LogFiles::scan(dir)?;
+
+		let now = Instant::now();
+		let (num_replayed, num_skipped) = replay(&files, cache)?;
+
+		if num_replayed > 0 {
+			info!("Replayed {num_replayed} operation(s) in {:?}", now.elapsed());
+		}
+
+		if num_skipped > 0 {
+			info!("Skipped {num_skipped} operation(s) which could not be replayed");
+		}
+
+		// replaying the log should not count towards the cache's statistics
+		cache.status.restore_counters(&StatusCounters::default());
+
+		let segment = Segment::create(dir, files.next_seq())?;
+
+		let op_log = Arc::new(FileOpLog {
+			dir: dir.to_path_buf(),
+			fsync,
+
+			segment: Mutex::new(segment),
+			objects: cache.objects.clone(),
+
+			key_locks:     (0..NUM_KEY_LOCKS).map(|_| Mutex::default()).collect(),
+			rotation_lock: RwLock::default(),
+			is_compacting: AtomicBool::new(false),
+
+			worker_handle: Mutex::default(),
+		});
+
+		let worker_handle = register_worker(OpLogWorker {
+			op_log: Arc::downgrade(&op_log),
+		});
+
+		*op_log.worker_handle.lock() = Some(worker_handle);
+
+		Ok(op_log)
+	}
+
+	fn rotate(&self) -> Result<u64, CacheError> {
+		// waits for any in-flight operations to be applied so that every
+		// operation in the older segments is reflected in the objects
+		let _rotation_guard = self.rotation_lock.write();
+
+		let mut segment = self.segment.lock();
+		let seq = segment.seq + 1;
+
+		segment.sync()?;
+		*segment = Segment::create(&self.dir, seq)?;
+
+		Ok(seq)
+	}
+
+	fn write_base(&self, seq: u64) -> Result<(), CacheError> {
+		let temp_path = log_path(&self.dir, seq, TEMP_EXTENSION);
+		let base_path = log_path(&self.dir, seq, BASE_EXTENSION);
+
+		let mut writer = match File::create(&temp_path) {
+			Ok(file) => BufWriter::new(file),
+
+			Err(err) => {
+				error!("Could not create operation log base: {err:?}");
+				return Err(CacheError::OpLogWrite);
+			},
+		};
+
+		// every operation in the older segments was applied before the
+		// rotation, while any operations applied while the objects are being
+		// written are also in the new segment, which is replayed on top of
+		// this base
+		let hashed_keys = self
+			.objects
+			.iter()
+			.map(|object| *object.key())
+			.collect::<Vec<_>>();
+
+		write_live_objects(
+			&self.objects,
+			hashed_keys,
+			|buffer, object| {
+				let value = object.data();
+
+				let record = LogRecord::Set(
+					object.key(),
+					&*value,
+					object.expiry().map(to_unix_millis),
+				);
+
+				write_record(buffer, &record).map(|_| ())
+			},
+			|buffer| {
+				writer.write_all(buffer).map_err(|err| {
+					error!("Could not write operation log base: {err:?}");
+					CacheError::OpLogWrite
+				})
+			},
+		)?;
+
+		let result = writer
+			.into_inner()
+			.map_err(|err| err.into_error())
+			.and_then(|file| file.sync_all())
+			.and_then(|_| fs::rename(&temp_path, &base_path));
+
+		if let Err(err) = result {
+			error!("Could not write operation log base: {err:?}");
+			return Err(CacheError::OpLogWrite);
+		}
+
+		// the base and the new segment now contain every operation, so all
+		// the older files can be removed
+		for (old_seq, path) in LogFiles::scan(&self.dir)?.all() {
+			if old_seq < seq {
+				fs::remove_file(path).ok();
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl<K, V> FileOpLog<K, V>
+where
+	K: Serialize,
+	V: Serialize,
+{
+	fn append(&self, record: &LogRecord<&K, &V>) -> Result<(), CacheError> {
+		let mut segment = self.segment.lock();
+		segment.size += write_record(&mut segment.writer, record)?;
+
+		if self.fsync == FsyncPolicy::Always {
+			segment.sync()?;
+		}
+
+		Ok(())
+	}
+
+	fn sync(&self) -> Result<(), CacheError> {
+		let mut segment = self.segment.lock();
+
+		match self.fsync {
+			FsyncPolicy::Never => segment.flush(),
+			_ => segment.sync(),
+		}
+	}
+
+	fn should_compact(&self) -> bool {
+		self.segment.lock().size >= COMPACTION_SIZE
+	}
+}
+
+impl<K, V> Drop for FileOpLog<K, V> {
+	fn drop(&mut self) {
+		if let Err(err) = self.segment.get_mut().sync() {
+			error!("Could not sync operation log on drop: {err:?}");
+		}
+	}
+}
+
+impl Segment {
+	fn create(dir: &Path, seq: u64) -> Result<Self, CacheError> {
+		let file = match File::create_new(log_path(dir, seq, SEGMENT_EXTENSION)) {
+			Ok(file) => file,
+
+			Err(err) => {
+				error!("Could not create operation log segment: {err:?}");
+				return Err(CacheError::OpLogWrite);
+			},
+		};
+
+		let segment = Segment {
+			seq,
+			size: 0,
+			writer: BufWriter::new(file),
+		};
+
+		Ok(segment)
+	}
+
+	fn flush(&mut self) -> Result<(), CacheError> {
+		self.writer.flush().map_err(|err| {
+			error!("Could not flush operation log: {err:?}");
+			CacheError::OpLogWrite
+		})
+	}
+
+	fn sync(&mut self) -> Result<(), CacheError> {
+		self.flush()?;
+
+		self.writer.get_ref().sync_data().map_err(|err| {
+			error!("Could not sync operation log: {err:?}");
+			CacheError::OpLogWrite
+		})
+	}
+}
+
+/// The segment and base files found in an operation log directory, each
+/// sorted by sequence number.
+struct LogFiles {
+	segments: Vec<(u64, PathBuf)>,
+	bases:    Vec<(u64, PathBuf)>,
+}
+
+impl LogFiles {
+	fn scan(dir: &Path) -> Result<Self, CacheError> {
+		let entries = match fs::read_dir(dir) {
+			Ok(entries) => entries,
+
+			Err(err) => {
+				error!("Could not read operation log directory: {err:?}");
+				return Err(CacheError::OpLogRead);
+			},
+		};
+
+		let mut files = LogFiles {
+			segments: Vec::new(),
+			bases:    Vec::new(),
+		};
+
+		for entry in entries.flatten() {
+			let path = entry.path();
+
+			let Some(seq) = path
+				.file_stem()
+				.and_then(|stem| stem.to_str())
+				.and_then(|stem| stem.parse::<u64>().ok())
+			else {
+				continue;
+			};
+
+			match path.extension().and_then(|extension| extension.to_str()) {
+				Some(SEGMENT_EXTENSION) => files.segments.push((seq, path)),
+				Some(BASE_EXTENSION) => files.bases.push((seq, path)),
+
+				// an incomplete base from an interrupted compaction
+				Some(TEMP_EXTENSION) => {
+					fs::remove_file(path).ok();
+				},
+
+				_ => {},
+			}
+		}
+
+		files.segments.sort_unstable();
+		files.bases.sort_unstable();
+
+		Ok(files)
+	}
+
+	/// Returns the files which must be replayed in order: the latest base
+	/// followed by all the segments which are not older than it.
+	fn replayable(&self) -> Vec<&Path> {
+		let base = self.bases.last();
+		let base_seq = base.map_or(0, |(seq, _)| *seq);
+
+		let segments = self
+			.segments
+			.iter()
+			.filter(|(seq, _)| *seq >= base_seq);
+
+		base.into_iter()
+			.chain(segments)
+			.map(|(_, path)| path.as_path())
+			.collect()
+	}
+
+	fn all(self) -> impl Iterator<Item = (u64, PathBuf)> {
+		self.segments.into_iter().chain(self.bases)
+	}
+
+	fn next_seq(&self) -> u64 {
+		self.all_seqs().max().map_or(0, |seq| seq + 1)
+	}
+
+	fn all_seqs(&self) -> impl Iterator<Item = u64> {
+		self.segments
+			.iter()
+			.chain(&self.bases)
+			.map(|(seq, _)| *seq)
+	}
+}
+
+impl<K, V> Worker for OpLogWorker<K, V>
+where
+	K: 'static + Eq + Hash + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+	V: 'static + TypeSize + Serialize + DeserializeOwned + Send + Sync,
+{
+	fn run(&mut self, shutdown: &ShutdownReceiver) -> Result<(), CacheError> {
+		while !sleep_or_shutdown(shutdown, POLL_DELAY) {
+			// the operation log was dropped along with the cache
+			let Some(op_log) = self.op_log.upgrade() else {
+				return Ok(());
+			};
+
+			if let Err(err) = op_log.sync() {
+				error!("Could not sync operation log: {err:?}");
+			}
+
+			if op_log.should_compact() {
+				info!("Compacting operation log");
+
+				if let Err(err) = op_log.compact() {
+					error!("Could not compact operation log: {err:?}");
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Replays the supplied log files into the cache, returning the number of
+/// operations which were applied and the number which were skipped.
+fn replay<K, V, S>(files: &LogFiles, cache: &PaperCache<K, V, S>) -> Result<(u64, u64), CacheError>
+where
+	K: 'static + Eq + Hash + TypeSize + DeserializeOwned,
+	V: 'static + TypeSize + DeserializeOwned,
+	S: Default + Clone + BuildHasher,
+{
+	let mut num_replayed = 0;
+	let mut num_skipped = 0;
+
+	for path in files.replayable() {
+		let file = match File::open(path) {
+			Ok(file) => file,
+
+			Err(err) => {
+				error!("Could not open operation log file: {err:?}");
+				return Err(CacheError::OpLogRead);
+			},
+		};
+
+		let mut reader = BufReader::new(file);
+
+		while let Some(record) = read_record::<K, V>(&mut reader)? {
+			let result = match record {
+				LogRecord::Set(key, value, expiry) => match expiry.map(to_ttl) {
+					Some(None) => cache.del(&key),
+					Some(ttl) => cache.set(key, value, ttl),
+					None => cache.set(key, value, None),
+				},
+
+				LogRecord::Del(key) => cache.del(&key),
+
+				LogRecord::Ttl(key, expiry) => match expiry.map(to_ttl) {
+					Some(None) => cache.del(&key),
+					Some(ttl) => cache.ttl(&key, ttl),
+					None => cache.ttl(&key, None),
+				},
+
+				LogRecord::Wipe => cache.wipe(),
+			};
+
+			// objects which no longer fit in the cache (e.g., because it was
+			// resized or its policy never evicts) are skipped
+			match result {
+				Ok(()) => num_replayed += 1,
+
+				Err(
+					CacheError::KeyNotFound
+					| CacheError::ZeroValueSize
+					| CacheError::ExceedingValueSize
+					| CacheError::Rejected
+					| CacheError::CacheFull,
+				) => num_skipped += 1,
+
+				Err(err) => return Err(err),
+			}
+		}
+	}
+
+	Ok((num_replayed, num_skipped))
+}
+
+fn write_record<K, V>(writer: &mut impl Write, record: &LogRecord<&K, &V>) -> Result<u64, CacheError>
+where
+	K: Serialize,
+	V: Serialize,
+{
+	let result = bincode::serialize(record)
+		.map_err(|err| io::Error::other(err.to_string()))
+		.and_then(|buf| writer.write_all(&buf).map(|_| buf.len() as u64));
+
+	result.map_err(|err| {
+		error!("Could not write to operation log: {err:?}");
+		CacheError::OpLogWrite
+	})
+}
+
+fn read_record<K, V>(reader: &mut BufReader<File>) -> Result<Option<LogRecord<K, V>>, CacheError>
+where
+	K: DeserializeOwned,
+	V: DeserializeOwned,
+{
+	match bincode::deserialize_from(reader) {
+		Ok(record) => Ok(Some(record)),
+
+		// the end of the file was reached, possibly in the middle of a record
+		// which was being written when the process stopped
+		Err(err) if is_eof(&err) => Ok(None),
+
+		Err(err) => {
+			error!("Could not read from operation log: {err:?}");
+			Err(CacheError::OpLogRead)
+		},
+	}
+}
+
+fn is_eof(err: &bincode::Error) -> bool {
+	matches!(&**err, bincode::ErrorKind::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof)
+}
+
+fn log_path(dir: &Path, seq: u64, extension: &str) -> PathBuf {
+	dir.join(format!("{seq:020}.{extension}"))
+}
+
+fn to_unix_millis(expiry: Instant) -> u64 {
+	let remaining = expiry.saturating_duration_since(Instant::now());
+	let expiry = SystemTime::now() + remaining;
+
+	expiry
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |duration| duration.as_millis() as u64)
+}
+
+/// Converts a logged expiry into a TTL in seconds (rounded up), or `None`
+/// if the expiry has already passed.
+fn to_ttl(unix_millis: u64) -> Option<u32> {
+	let expiry = UNIX_EPOCH + Duration::from_millis(unix_millis);
+	let remaining = expiry.duration_since(SystemTime::now()).ok()?;
+
+	match remaining.as_millis().div_ceil(1000) {
+		0 => None,
+		ttl => Some(ttl as u32),
+	}
+}
@@ -0,0 +1,72 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use crate::object::{ObjectCost, ObjectHints};
+
+/// The options of an object set with [`PaperCache::set_with_options`].
+///
+/// The miss cost and priority class are hints for the cost-aware eviction
+/// policies (e.g., [`PaperPolicy::Gdsf`]), and are ignored by the others.
+///
+/// [`PaperCache::set_with_options`]: crate::PaperCache::set_with_options
+/// [`PaperPolicy::Gdsf`]: crate::PaperPolicy::Gdsf
+///
+/// # Examples
+/// ```
+/// use paper_cache::{PaperCache, PaperPolicy, SetOptions};
+///
+/// let cache = PaperCache::<u32, u32>::new(
+///     1000,
+///     &[PaperPolicy::Gdsf],
+///     PaperPolicy::Gdsf,
+/// ).unwrap();
+///
+/// // expires after 60 seconds and takes 500 ms to recompute
+/// let options = SetOptions::new()
+///     .ttl(60)
+///     .cost(500);
+///
+/// assert!(cache.set_with_options(0, 0, options).is_ok());
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SetOptions {
+	pub(crate) ttl:   Option<u32>,
+	pub(crate) hints: ObjectHints,
+}
+
+impl SetOptions {
+	/// Creates options with no TTL, a miss cost of 1, and a priority class
+	/// of 0.
+	#[must_use]
+	pub fn new() -> Self {
+		SetOptions::default()
+	}
+
+	/// Sets the object's TTL in seconds. A TTL of zero means the object
+	/// does not expire.
+	#[must_use]
+	pub fn ttl(mut self, ttl: u32) -> Self {
+		self.ttl = Some(ttl);
+		self
+	}
+
+	/// Sets the cost of a miss on the object, in any unit which is consistent
+	/// across the cache's objects.
+	#[must_use]
+	pub fn cost(mut self, cost: ObjectCost) -> Self {
+		self.hints.cost = cost;
+		self
+	}
+
+	/// Sets the object's priority class. Cost-aware policies evict objects
+	/// in lower priority classes before objects in higher ones.
+	#[must_use]
+	pub fn priority(mut self, priority: u8) -> Self {
+		self.hints.priority = priority;
+		self
+	}
+}
@@ -6,8 +6,11 @@
  */
 
 use std::{
+	collections::HashMap,
 	fmt::{self, Display},
 	str::FromStr,
+	sync::Arc,
+	time::Duration,
 };
 
 use serde::{
@@ -15,7 +18,26 @@ use serde::{
 	de::{self, Deserializer, Visitor},
 };
 
-use crate::error::CacheError;
+use crate::{
+	CacheSize,
+	PolicyStack,
+	error::CacheError,
+	object::{ObjectSize, overhead::get_policy_overhead},
+};
+
+pub type PolicyStackFactory = Arc<dyn Fn(CacheSize) -> Box<dyn PolicyStack> + Send + Sync>;
+pub type CustomPoliciesRef = Arc<CustomPolicies>;
+
+/// The custom policies registered with a cache's builder, keyed by name.
+#[derive(Default)]
+pub struct CustomPolicies {
+	policies: HashMap<&'static str, CustomPolicy>,
+}
+
+struct CustomPolicy {
+	overhead: ObjectSize,
+	factory:  PolicyStackFactory,
+}
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum PaperPolicy {
@@ -29,12 +51,222 @@ pub enum PaperPolicy {
 	TwoQ(f64, f64),
 	Arc,
 	SThreeFifo(f64),
+	WTinyLfu(f64),
+	Lirs(f64),
+	LruK(u32),
+	Gdsf,
+	SampledLru(u32),
+	SampledLfu(u32),
+	Random,
+	LfuDa,
+	Slru(f64),
+	MultiClock(u8),
+	VolatileTtl,
+	NoEviction,
+
+	/// A policy registered with [`PaperCacheBuilder::custom_policy`](crate::PaperCacheBuilder::custom_policy).
+	Custom(&'static str),
+}
+
+/// The quantity which the [`PaperPolicy::Auto`] policy minimizes when it
+/// selects one of the configured policies.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum AutoPolicyObjective {
+	/// The ratio of gets which miss.
+	#[default]
+	MissRatio,
+
+	/// The total cost of the gets which miss, where each object's cost of a
+	/// miss is supplied with [`SetOptions::cost`](crate::SetOptions::cost).
+	MissCost,
+}
+
+/// Configures how the [`PaperPolicy::Auto`] policy selects one of the
+/// configured policies.
+///
+/// Each of the configured policies is simulated on a sample of the cache's
+/// keys. At the end of every epoch, the cache switches to the policy with the
+/// lowest simulated miss ratio (or miss cost) if it improves on the current
+/// policy by at least the minimum improvement, and no other policy was
+/// selected during the cooldown.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use paper_cache::AutoPolicyConfig;
+///
+/// // switch only on a 5% reduction in the miss ratio, at most once an hour
+/// let config = AutoPolicyConfig::new()
+///     .epoch(Duration::from_secs(300))
+///     .min_improvement(0.05)
+///     .cooldown(Duration::from_secs(3_600));
+/// ```
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct AutoPolicyConfig {
+	pub(crate) epoch:           Duration,
+	pub(crate) sample_rate:     f64,
+	pub(crate) min_improvement: f64,
+	pub(crate) cooldown:        Duration,
+	pub(crate) objective:       AutoPolicyObjective,
+}
+
+impl AutoPolicyConfig {
+	/// Creates a configuration with an epoch of one hour, a sample rate of
+	/// 0.1%, no minimum improvement, and no cooldown.
+	#[must_use]
+	pub fn new() -> Self {
+		AutoPolicyConfig::default()
+	}
+
+	/// Sets the duration after which the policies are compared.
+	#[must_use]
+	pub fn epoch(mut self, epoch: Duration) -> Self {
+		self.epoch = epoch;
+		self
+	}
+
+	/// Sets the fraction of the cache's keys on which the policies are
+	/// simulated, which is clamped between 0 (exclusive) and 1. A higher
+	/// sample rate improves the accuracy of the simulations at the cost of
+	/// more memory and processing.
+	#[must_use]
+	pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+		self.sample_rate = sample_rate;
+		self
+	}
+
+	/// Sets the relative reduction of the miss ratio (or miss cost) which a
+	/// policy must achieve over the current policy to be selected (e.g., 0.05
+	/// for a 5% reduction). Defaults to 0, in which case any policy which is
+	/// strictly better is selected.
+	#[must_use]
+	pub fn min_improvement(mut self, min_improvement: f64) -> Self {
+		self.min_improvement = min_improvement.clamp(0.0, 1.0);
+		self
+	}
+
+	/// Sets the minimum duration between two automatic policy switches.
+	#[must_use]
+	pub fn cooldown(mut self, cooldown: Duration) -> Self {
+		self.cooldown = cooldown;
+		self
+	}
+
+	/// Sets the quantity which the policy selection minimizes.
+	#[must_use]
+	pub fn objective(mut self, objective: AutoPolicyObjective) -> Self {
+		self.objective = objective;
+		self
+	}
+}
+
+impl Default for AutoPolicyConfig {
+	fn default() -> Self {
+		AutoPolicyConfig {
+			epoch:           Duration::from_secs(3_600),
+			sample_rate:     0.001,
+			min_improvement: 0.0,
+			cooldown:        Duration::ZERO,
+			objective:       AutoPolicyObjective::default(),
+		}
+	}
+}
+
+/// A record of one of the [`PaperPolicy::Auto`] policy's epochs, in which
+/// the configured policies were compared.
+#[derive(PartialEq, Clone, Debug)]
+pub struct AutoPolicyDecision {
+	pub(crate) timestamp:      u64,
+	pub(crate) miss_ratios:    Box<[(PaperPolicy, f64)]>,
+	pub(crate) num_samples:    u64,
+	pub(crate) current_policy: PaperPolicy,
+	pub(crate) chosen_policy:  PaperPolicy,
+	pub(crate) reason:         AutoPolicyReason,
+}
+
+/// The reason the [`PaperPolicy::Auto`] policy did or did not switch
+/// policies at the end of an epoch.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AutoPolicyReason {
+	/// The chosen policy improved on the current policy by at least the
+	/// minimum improvement, so the cache switched to it.
+	Switched,
+
+	/// No gets were sampled during the epoch.
+	NoSamples,
+
+	/// No policy performed better than the current policy.
+	AlreadyOptimal,
+
+	/// The chosen policy performed better than the current policy, but not
+	/// by the minimum improvement.
+	BelowMinImprovement,
+
+	/// The chosen policy would have been switched to, but the cache already
+	/// switched policies during the cooldown.
+	Cooldown,
+}
+
+impl AutoPolicyDecision {
+	/// Returns the time at which the policies were compared, in milliseconds
+	/// since the UNIX epoch.
+	#[must_use]
+	pub fn timestamp(&self) -> u64 {
+		self.timestamp
+	}
+
+	/// Returns the estimated miss ratio of each of the simulated policies
+	/// during the epoch, relative to the number of gets which were sampled
+	/// during the epoch. If the objective is [`AutoPolicyObjective::MissCost`],
+	/// the ratio of each policy's miss cost to the total cost is returned.
+	#[must_use]
+	pub fn miss_ratios(&self) -> &[(PaperPolicy, f64)] {
+		&self.miss_ratios
+	}
+
+	/// Returns the number of gets which were sampled during the epoch.
+	#[must_use]
+	pub fn num_samples(&self) -> u64 {
+		self.num_samples
+	}
+
+	/// Returns the cache's policy at the end of the epoch.
+	#[must_use]
+	pub fn current_policy(&self) -> PaperPolicy {
+		self.current_policy
+	}
+
+	/// Returns the policy with the lowest estimated miss ratio, which the
+	/// cache switched to if the reason is [`AutoPolicyReason::Switched`].
+	#[must_use]
+	pub fn chosen_policy(&self) -> PaperPolicy {
+		self.chosen_policy
+	}
+
+	/// Returns the reason the cache did or did not switch policies.
+	#[must_use]
+	pub fn reason(&self) -> AutoPolicyReason {
+		self.reason
+	}
+
+	/// Returns `true` if the cache switched to the chosen policy.
+	#[must_use]
+	pub fn is_switch(&self) -> bool {
+		self.reason == AutoPolicyReason::Switched
+	}
 }
 
 impl PaperPolicy {
 	pub fn is_auto(&self) -> bool {
 		matches!(self, PaperPolicy::Auto)
 	}
+
+	/// Returns `true` if the policy never evicts objects, in which case sets
+	/// are rejected once the cache is full.
+	pub fn is_no_eviction(&self) -> bool {
+		matches!(self, PaperPolicy::NoEviction)
+	}
 }
 
 impl Display for PaperPolicy {
@@ -50,6 +282,19 @@ impl Display for PaperPolicy {
 			PaperPolicy::TwoQ(k_in, k_out) => write!(f, "2q-{k_in}-{k_out}"),
 			PaperPolicy::Arc => write!(f, "arc"),
 			PaperPolicy::SThreeFifo(ratio) => write!(f, "s3-fifo-{ratio}"),
+			PaperPolicy::WTinyLfu(ratio) => write!(f, "w-tinylfu-{ratio}"),
+			PaperPolicy::Lirs(hir_ratio) => write!(f, "lirs-{hir_ratio}"),
+			PaperPolicy::LruK(k) => write!(f, "lru-{k}"),
+			PaperPolicy::Gdsf => write!(f, "gdsf"),
+			PaperPolicy::SampledLru(samples) => write!(f, "sampled-lru-{samples}"),
+			PaperPolicy::SampledLfu(samples) => write!(f, "sampled-lfu-{samples}"),
+			PaperPolicy::Random => write!(f, "random"),
+			PaperPolicy::LfuDa => write!(f, "lfu-da"),
+			PaperPolicy::Slru(ratio) => write!(f, "slru-{ratio}"),
+			PaperPolicy::MultiClock(bits) => write!(f, "clock-{bits}"),
+			PaperPolicy::VolatileTtl => write!(f, "volatile-ttl"),
+			PaperPolicy::NoEviction => write!(f, "no-eviction"),
+			PaperPolicy::Custom(name) => write!(f, "{name}"),
 		}
 	}
 }
@@ -66,9 +311,20 @@ impl FromStr for PaperPolicy {
 			"sieve" => PaperPolicy::Sieve,
 			"lru" => PaperPolicy::Lru,
 			"mru" => PaperPolicy::Mru,
+			"gdsf" => PaperPolicy::Gdsf,
+			"random" => PaperPolicy::Random,
+			"lfu-da" => PaperPolicy::LfuDa,
+			"volatile-ttl" => PaperPolicy::VolatileTtl,
+			"no-eviction" => PaperPolicy::NoEviction,
 			value if value.starts_with("2q-") => parse_two_q(value)?,
 			"arc" => PaperPolicy::Arc,
 			value if value.starts_with("s3-fifo-") => parse_s_three_fifo(value)?,
+			value if value.starts_with("w-tinylfu-") => parse_w_tinylfu(value)?,
+			value if value.starts_with("lirs-") => parse_lirs(value)?,
+			value if value.starts_with("lru-") => parse_lru_k(value)?,
+			value if value.starts_with("sampled-") => parse_sampled(value)?,
+			value if value.starts_with("slru-") => parse_slru(value)?,
+			value if value.starts_with("clock-") => parse_multi_clock(value)?,
 
 			_ => return Err(CacheError::InvalidPolicy),
 		};
@@ -144,3 +400,185 @@ fn parse_s_three_fifo(value: &str) -> Result<PaperPolicy, CacheError> {
 
 	Ok(PaperPolicy::SThreeFifo(ratio))
 }
+
+fn parse_w_tinylfu(value: &str) -> Result<PaperPolicy, CacheError> {
+	// skip the "w-tinylfu-"
+	let tokens = value[10..].split('-').collect::<Vec<&str>>();
+
+	if tokens.len() != 1 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	let Ok(window_ratio) = tokens[0].parse::<f64>() else {
+		return Err(CacheError::InvalidPolicy);
+	};
+
+	if !(0.0..=1.0).contains(&window_ratio) {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	Ok(PaperPolicy::WTinyLfu(window_ratio))
+}
+
+fn parse_lirs(value: &str) -> Result<PaperPolicy, CacheError> {
+	// skip the "lirs-"
+	let tokens = value[5..].split('-').collect::<Vec<&str>>();
+
+	if tokens.len() != 1 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	let Ok(hir_ratio) = tokens[0].parse::<f64>() else {
+		return Err(CacheError::InvalidPolicy);
+	};
+
+	if !(0.0..=1.0).contains(&hir_ratio) {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	Ok(PaperPolicy::Lirs(hir_ratio))
+}
+
+fn parse_lru_k(value: &str) -> Result<PaperPolicy, CacheError> {
+	// skip the "lru-"
+	let tokens = value[4..].split('-').collect::<Vec<&str>>();
+
+	if tokens.len() != 1 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	let Ok(k) = tokens[0].parse::<u32>() else {
+		return Err(CacheError::InvalidPolicy);
+	};
+
+	if k == 0 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	Ok(PaperPolicy::LruK(k))
+}
+
+fn parse_sampled(value: &str) -> Result<PaperPolicy, CacheError> {
+	// skip the "sampled-"
+	let tokens = value[8..].split('-').collect::<Vec<&str>>();
+
+	if tokens.len() != 2 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	let Ok(samples) = tokens[1].parse::<u32>() else {
+		return Err(CacheError::InvalidPolicy);
+	};
+
+	if samples == 0 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	match tokens[0] {
+		"lru" => Ok(PaperPolicy::SampledLru(samples)),
+		"lfu" => Ok(PaperPolicy::SampledLfu(samples)),
+
+		_ => Err(CacheError::InvalidPolicy),
+	}
+}
+
+fn parse_slru(value: &str) -> Result<PaperPolicy, CacheError> {
+	// skip the "slru-"
+	let tokens = value[5..].split('-').collect::<Vec<&str>>();
+
+	if tokens.len() != 1 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	let Ok(protected_ratio) = tokens[0].parse::<f64>() else {
+		return Err(CacheError::InvalidPolicy);
+	};
+
+	if !(0.0..=1.0).contains(&protected_ratio) {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	Ok(PaperPolicy::Slru(protected_ratio))
+}
+
+fn parse_multi_clock(value: &str) -> Result<PaperPolicy, CacheError> {
+	// skip the "clock-"
+	let tokens = value[6..].split('-').collect::<Vec<&str>>();
+
+	if tokens.len() != 1 {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	let Ok(bits) = tokens[0].parse::<u8>() else {
+		return Err(CacheError::InvalidPolicy);
+	};
+
+	if !(1..=8).contains(&bits) {
+		return Err(CacheError::InvalidPolicy);
+	}
+
+	Ok(PaperPolicy::MultiClock(bits))
+}
+
+impl CustomPolicies {
+	/// Registers a custom policy. Returns a [`CacheError`] if the name is
+	/// empty, is the name of a built-in policy, or is already registered.
+	pub fn register(
+		&mut self,
+		name: &'static str,
+		overhead: ObjectSize,
+		factory: PolicyStackFactory,
+	) -> Result<(), CacheError> {
+		if name.is_empty() || name.parse::<PaperPolicy>().is_ok() {
+			return Err(CacheError::InvalidPolicy);
+		}
+
+		if self.policies.contains_key(name) {
+			return Err(CacheError::DuplicatePolicies);
+		}
+
+		let custom_policy = CustomPolicy {
+			overhead,
+			factory,
+		};
+
+		self.policies.insert(name, custom_policy);
+
+		Ok(())
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.policies.contains_key(name)
+	}
+
+	pub fn init_stack(&self, name: &str, max_size: CacheSize) -> Option<Box<dyn PolicyStack>> {
+		self.policies
+			.get(name)
+			.map(|custom_policy| (custom_policy.factory)(max_size))
+	}
+
+	/// Returns the per-object overhead of the supplied policy, which is
+	/// supplied when it is registered if it is a custom policy.
+	pub fn policy_overhead(&self, policy: &PaperPolicy) -> ObjectSize {
+		match policy {
+			PaperPolicy::Custom(name) => self
+				.policies
+				.get(name)
+				.map(|custom_policy| custom_policy.overhead)
+				.unwrap_or_default(),
+
+			policy => get_policy_overhead(policy),
+		}
+	}
+
+	/// Parses the supplied value into a [`PaperPolicy::Custom`] if it is the
+	/// name of a registered policy, or into a built-in policy otherwise.
+	pub fn parse(&self, value: &str) -> Result<PaperPolicy, CacheError> {
+		// custom policies may share the prefix of a built-in policy (but never
+		// its full name), so they are matched first
+		match self.policies.get_key_value(value) {
+			Some((name, _)) => Ok(PaperPolicy::Custom(name)),
+			None => value.parse(),
+		}
+	}
+}
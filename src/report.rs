@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use crate::policy::PaperPolicy;
+
+// the z-score of the 95% confidence intervals
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// A comparison of the configured policies, each of which is simulated on a
+/// sample of the cache's keys.
+///
+/// If the cache's policy is [`PaperPolicy::Auto`], the estimates cover the
+/// gets which were sampled since the end of its most recent epoch. Otherwise,
+/// no epochs end, so the estimates cover every get which was sampled since
+/// the cache was created, wiped, or had its sample rate changed.
+///
+/// The no-eviction policy is never simulated, so it has no estimate.
+#[derive(PartialEq, Clone, Debug)]
+pub struct PolicyReport {
+	pub(crate) num_samples: u64,
+	pub(crate) estimates:   Box<[PolicyEstimate]>,
+}
+
+/// The estimated performance of one of the configured policies.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct PolicyEstimate {
+	pub(crate) policy:              PaperPolicy,
+	pub(crate) miss_ratio:          f64,
+	pub(crate) miss_ratio_interval: (f64, f64),
+	pub(crate) byte_miss_ratio:     f64,
+}
+
+impl PolicyReport {
+	/// Returns the number of sampled gets on which the estimates are based.
+	#[must_use]
+	pub fn num_samples(&self) -> u64 {
+		self.num_samples
+	}
+
+	/// Returns the estimates of each of the simulated policies.
+	#[must_use]
+	pub fn estimates(&self) -> &[PolicyEstimate] {
+		&self.estimates
+	}
+
+	/// Returns the estimate of the supplied policy, if it is simulated.
+	#[must_use]
+	pub fn estimate(&self, policy: PaperPolicy) -> Option<&PolicyEstimate> {
+		self.estimates
+			.iter()
+			.find(|estimate| estimate.policy == policy)
+	}
+}
+
+impl PolicyEstimate {
+	/// Returns the simulated policy.
+	#[must_use]
+	pub fn policy(&self) -> PaperPolicy {
+		self.policy
+	}
+
+	/// Returns the policy's estimated miss ratio.
+	#[must_use]
+	pub fn miss_ratio(&self) -> f64 {
+		self.miss_ratio
+	}
+
+	/// Returns the lower and upper bounds of the 95% confidence interval of
+	/// the policy's miss ratio, which narrows as the number of samples grows.
+	#[must_use]
+	pub fn miss_ratio_interval(&self) -> (f64, f64) {
+		self.miss_ratio_interval
+	}
+
+	/// Returns the policy's estimated byte miss ratio, which is the ratio of
+	/// the missed objects' sizes to the total size of the gotten objects.
+	/// Gets of keys whose sizes are unknown to the simulation (e.g., keys
+	/// which were never set) are not included.
+	#[must_use]
+	pub fn byte_miss_ratio(&self) -> f64 {
+		self.byte_miss_ratio
+	}
+}
+
+/// Returns the Wilson score interval of the supplied ratio, which (unlike
+/// the normal approximation) remains within [0, 1] for small sample counts
+/// and ratios near 0 or 1.
+pub fn get_confidence_interval(ratio: f64, num_samples: u64) -> (f64, f64) {
+	if num_samples == 0 {
+		return (0.0, 1.0);
+	}
+
+	let n = num_samples as f64;
+	let z_squared = CONFIDENCE_Z * CONFIDENCE_Z;
+
+	let denominator = 1.0 + z_squared / n;
+	let center = (ratio + z_squared / (2.0 * n)) / denominator;
+
+	let margin = CONFIDENCE_Z
+		* (ratio * (1.0 - ratio) / n + z_squared / (4.0 * n * n)).sqrt()
+		/ denominator;
+
+	((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn confidence_interval_is_correct() {
+		use crate::report::get_confidence_interval;
+
+		assert_eq!(get_confidence_interval(0.5, 0), (0.0, 1.0));
+
+		let (lower, upper) = get_confidence_interval(0.5, 100);
+		assert!((lower - 0.4038).abs() < 0.001);
+		assert!((upper - 0.5962).abs() < 0.001);
+
+		// the interval narrows as the number of samples grows
+		let (lower, upper) = get_confidence_interval(0.5, 10_000);
+		assert!(lower > 0.49 && upper < 0.51);
+	}
+}
@@ -0,0 +1,175 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use crate::HashedKey;
+
+const SKETCH_DEPTH: usize = 4;
+const MAX_COUNT: u8 = 15;
+
+// the number of additions per counter after which the counters are aged
+const SAMPLE_FACTOR: usize = 10;
+
+const DOORKEEPER_SEED: u64 = 0x27d4_eb2f_1656_67c5;
+
+const SEEDS: [u64; SKETCH_DEPTH] = [
+	0x9e37_79b9_7f4a_7c15,
+	0xc2b2_ae3d_27d4_eb4f,
+	0x1656_67b1_9e37_79f9,
+	0x85eb_ca77_c2b2_ae63,
+];
+
+/// An approximate frequency counter for hashed keys. A count-min sketch of
+/// 4-bit counters is preceded by a doorkeeper bloom filter so that keys
+/// which are only accessed once do not occupy the counters. Once the number
+/// of additions reaches the sample size, all frequencies are halved so that
+/// the sketch favors recent accesses.
+pub struct FrequencySketch {
+	counters:   Box<[u8]>,
+	doorkeeper: Box<[u64]>,
+	width_mask: u64,
+
+	additions:   usize,
+	sample_size: usize,
+}
+
+impl FrequencySketch {
+	/// Creates a sketch sized for approximately `capacity` distinct keys.
+	pub fn new(capacity: usize) -> Self {
+		let width = capacity.max(16).next_power_of_two();
+
+		FrequencySketch {
+			counters:   vec![0; SKETCH_DEPTH * width].into(),
+			doorkeeper: vec![0; width.div_ceil(64)].into(),
+			width_mask: width as u64 - 1,
+
+			additions:   0,
+			sample_size: width * SAMPLE_FACTOR,
+		}
+	}
+
+	/// Records an access of the supplied key.
+	pub fn increment(&mut self, key: HashedKey) {
+		if self.doorkeeper_insert(key) {
+			let indices = self.counter_indices(key);
+			let min_count = self.min_count(&indices);
+
+			// only the minimal counters are incremented (conservative update)
+			// to reduce the overestimation caused by collisions
+			if min_count < MAX_COUNT {
+				for index in indices {
+					if self.counters[index] == min_count {
+						self.counters[index] += 1;
+					}
+				}
+			}
+		}
+
+		self.additions += 1;
+
+		if self.additions >= self.sample_size {
+			self.age();
+		}
+	}
+
+	/// Returns the estimated number of accesses of the supplied key.
+	pub fn frequency(&self, key: HashedKey) -> u8 {
+		let count = self.min_count(&self.counter_indices(key));
+		count + u8::from(self.doorkeeper_contains(key))
+	}
+
+	pub fn clear(&mut self) {
+		self.counters.fill(0);
+		self.doorkeeper.fill(0);
+		self.additions = 0;
+	}
+
+	fn age(&mut self) {
+		for counter in &mut self.counters {
+			*counter /= 2;
+		}
+
+		self.doorkeeper.fill(0);
+		self.additions /= 2;
+	}
+
+	fn min_count(&self, indices: &[usize; SKETCH_DEPTH]) -> u8 {
+		indices
+			.iter()
+			.map(|index| self.counters[*index])
+			.min()
+			.unwrap_or_default()
+	}
+
+	fn counter_indices(&self, key: HashedKey) -> [usize; SKETCH_DEPTH] {
+		let width = self.width_mask as usize + 1;
+
+		std::array::from_fn(|row| row * width + self.column(key, row))
+	}
+
+	fn column(&self, key: HashedKey, row: usize) -> usize {
+		let hash = key.wrapping_mul(SEEDS[row]);
+		((hash >> 32) & self.width_mask) as usize
+	}
+
+	/// Sets the key's bit in the doorkeeper, returning `true` if it was
+	/// already set.
+	fn doorkeeper_insert(&mut self, key: HashedKey) -> bool {
+		let (index, mask) = self.doorkeeper_bit(key);
+		let is_set = self.doorkeeper[index] & mask != 0;
+
+		self.doorkeeper[index] |= mask;
+		is_set
+	}
+
+	fn doorkeeper_contains(&self, key: HashedKey) -> bool {
+		let (index, mask) = self.doorkeeper_bit(key);
+		self.doorkeeper[index] & mask != 0
+	}
+
+	fn doorkeeper_bit(&self, key: HashedKey) -> (usize, u64) {
+		let hash = key.wrapping_mul(DOORKEEPER_SEED);
+		let bit = ((hash >> 32) & self.width_mask) as usize;
+		(bit / 64, 1 << (bit % 64))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::sketch::FrequencySketch;
+
+	#[test]
+	fn it_estimates_frequencies() {
+		let mut sketch = FrequencySketch::new(1024);
+
+		for _ in 0..5 {
+			sketch.increment(1);
+		}
+
+		sketch.increment(2);
+
+		assert_eq!(sketch.frequency(1), 5);
+		assert_eq!(sketch.frequency(2), 1);
+		assert_eq!(sketch.frequency(3), 0);
+	}
+
+	#[test]
+	fn it_ages_frequencies() {
+		let mut sketch = FrequencySketch::new(1024);
+
+		for _ in 0..9 {
+			sketch.increment(1);
+		}
+
+		// the sample size of the sketch is 10,240 additions
+		for _ in 9..10_240 {
+			sketch.increment(2);
+		}
+
+		assert_eq!(sketch.frequency(1), 4);
+		assert_eq!(sketch.frequency(2), 7);
+	}
+}
@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	collections::HashSet,
+	hash::{BuildHasher, Hash},
+	io::{BufReader, BufWriter, Read, Write},
+};
+
+use crossbeam_channel::bounded;
+use log::error;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use typesize::TypeSize;
+
+use crate::{
+	CacheError,
+	HashedKey,
+	NoHasher,
+	PaperCache,
+	object::write_live_objects,
+	status::StatusCounters,
+	worker::WorkerEvent,
+};
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader {
+	version:  u32,
+	counters: StatusCounters,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotObject<K, V> {
+	key:   K,
+	value: V,
+	ttl:   Option<u32>,
+}
+
+impl<K, V, S> PaperCache<K, V, S>
+where
+	K: 'static + Eq + Hash + TypeSize + Serialize,
+	V: 'static + TypeSize + Serialize,
+	S: Default + Clone + BuildHasher,
+{
+	/// Writes a snapshot of the cache's objects (with their remaining TTLs)
+	/// and statistics to the supplied writer.
+	///
+	/// The objects are written in the order in which the current policy would
+	/// evict them so that loading the snapshot reproduces an approximation
+	/// of the cache's eviction state.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// cache.set(0, 1, None);
+	///
+	/// let mut snapshot = Vec::new();
+	/// assert!(cache.save_snapshot(&mut snapshot).is_ok());
+	/// ```
+	pub fn save_snapshot(&self, writer: impl Write) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+
+		let mut writer = BufWriter::new(writer);
+
+		let header = SnapshotHeader {
+			version:  SNAPSHOT_VERSION,
+			counters: self.status.counters(),
+		};
+
+		write_item(&mut writer, &header)?;
+
+		// any objects which are not part of the eviction order (e.g., because
+		// they have not yet been traced) are written last since they were the
+		// most recently accessed
+		let remaining_keys = self
+			.objects
+			.iter()
+			.map(|object| *object.key())
+			.collect::<Vec<_>>();
+
+		let mut written_keys = HashSet::<HashedKey, NoHasher>::default();
+
+		let hashed_keys = self
+			.eviction_order()
+			.into_iter()
+			.chain(remaining_keys)
+			.filter(|hashed_key| written_keys.insert(*hashed_key));
+
+		write_live_objects(
+			&self.objects,
+			hashed_keys,
+			|buffer, object| {
+				let value = object.data();
+
+				let snapshot_object = SnapshotObject {
+					key:   object.key(),
+					value: &*value,
+					ttl:   object.remaining_ttl(),
+				};
+
+				write_item(buffer, &Some(snapshot_object))
+			},
+			|buffer| {
+				writer.write_all(buffer).map_err(|err| {
+					error!("Could not write snapshot: {err:?}");
+					CacheError::SnapshotWrite
+				})
+			},
+		)?;
+
+		write_item(&mut writer, &None::<SnapshotObject<&K, &V>>)?;
+
+		writer.flush().map_err(|err| {
+			error!("Could not flush snapshot: {err:?}");
+			CacheError::SnapshotWrite
+		})
+	}
+
+	/// Returns the hashed keys of the current policy ordered from the first
+	/// to the last to be evicted. If the order could not be computed, an
+	/// empty order is returned.
+	fn eviction_order(&self) -> Vec<HashedKey> {
+		let (sender, receiver) = bounded(1);
+
+		if self.broadcast(WorkerEvent::EvictionOrder(sender)).is_err() {
+			return Vec::new();
+		}
+
+		receiver.recv().unwrap_or_default()
+	}
+}
+
+impl<K, V, S> PaperCache<K, V, S>
+where
+	K: 'static + Eq + Hash + TypeSize + DeserializeOwned,
+	V: 'static + TypeSize + DeserializeOwned,
+	S: Default + Clone + BuildHasher,
+{
+	/// Loads a snapshot written by [`PaperCache::save_snapshot`] from the
+	/// supplied reader, setting each of its objects and restoring the cache's
+	/// statistics.
+	///
+	/// Objects which do not fit in the cache (or which are rejected by the
+	/// cache's admission policy, or which would exceed the capacity of a
+	/// cache which never evicts) are skipped. If the snapshot
+	/// contains more objects than the cache can hold, the objects which were
+	/// closest to eviction are evicted first.
+	///
+	/// # Examples
+	/// ```
+	/// use paper_cache::{PaperCache, PaperPolicy};
+	///
+	/// let cache = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// cache.set(0, 1, None);
+	///
+	/// let mut snapshot = Vec::new();
+	/// cache.save_snapshot(&mut snapshot).unwrap();
+	///
+	/// let restored = PaperCache::<u32, u32>::new(
+	///     1000,
+	///     &[PaperPolicy::Lfu],
+	///     PaperPolicy::Lfu,
+	/// ).unwrap();
+	///
+	/// assert!(restored.load_snapshot(snapshot.as_slice()).is_ok());
+	/// assert_eq!(restored.get(&0), Ok(1.into()));
+	/// ```
+	pub fn load_snapshot(&self, reader: impl Read) -> Result<(), CacheError> {
+		self.check_shutdown()?;
+
+		let mut reader = BufReader::new(reader);
+		let header = read_item::<_, SnapshotHeader>(&mut reader)?;
+
+		if header.version != SNAPSHOT_VERSION {
+			error!("Unsupported snapshot version {}", header.version);
+			return Err(CacheError::SnapshotRead);
+		}
+
+		while let Some(object) = read_item::<_, Option<SnapshotObject<K, V>>>(&mut reader)? {
+			match self.set(object.key, object.value, object.ttl) {
+				Ok(())
+				| Err(
+					CacheError::ZeroValueSize
+					| CacheError::ExceedingValueSize
+					| CacheError::Rejected
+					| CacheError::CacheFull,
+				) => {},
+				Err(err) => return Err(err),
+			}
+		}
+
+		self.status.restore_counters(&header.counters);
+
+		Ok(())
+	}
+}
+
+fn write_item<T>(writer: &mut impl Write, item: &T) -> Result<(), CacheError>
+where
+	T: Serialize,
+{
+	bincode::serialize_into(writer, item).map_err(|err| {
+		error!("Could not write snapshot: {err:?}");
+		CacheError::SnapshotWrite
+	})
+}
+
+fn read_item<R, T>(reader: &mut R) -> Result<T, CacheError>
+where
+	R: Read,
+	T: DeserializeOwned,
+{
+	bincode::deserialize_from(reader).map_err(|err| {
+		error!("Could not read snapshot: {err:?}");
+		CacheError::SnapshotRead
+	})
+}
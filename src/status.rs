@@ -6,6 +6,7 @@
  */
 
 use std::{
+	collections::VecDeque,
 	process,
 	sync::{
 		Arc,
@@ -16,15 +17,21 @@ use std::{
 use kwik::{sys::mem, time};
 use log::error;
 use num_traits::AsPrimitive;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 
 use crate::{
 	AtomicCacheSize,
 	CacheSize,
+	HashedKey,
 	error::CacheError,
-	object::overhead::get_policy_overhead,
-	policy::PaperPolicy,
+	object::ObjectSize,
+	policy::{AutoPolicyDecision, CustomPoliciesRef, PaperPolicy},
 };
 
+// the number of the most recent auto policy decisions which are kept
+const AUTO_POLICY_HISTORY_SIZE: usize = 128;
+
 #[derive(Debug)]
 pub struct Status {
 	pid: u32,
@@ -41,10 +48,15 @@ pub struct Status {
 	total_sets: u64,
 	total_dels: u64,
 
+	dropped_gets:  u64,
+	rejected_sets: u64,
+
 	policies:       Arc<[PaperPolicy]>,
 	policy:         PaperPolicy,
 	is_auto_policy: bool,
 
+	auto_policy_history: Box<[AutoPolicyDecision]>,
+
 	start_time: u64,
 }
 
@@ -58,13 +70,31 @@ pub struct AtomicStatus {
 	total_sets: AtomicU64,
 	total_dels: AtomicU64,
 
-	policies:       Arc<[PaperPolicy]>,
-	policy_index:   AtomicUsize,
-	is_auto_policy: AtomicBool,
+	dropped_gets:  AtomicU64,
+	rejected_sets: AtomicU64,
+
+	// the hashed key of the most recently evicted object, against which
+	// the admission policy compares new objects
+	last_eviction: Mutex<Option<HashedKey>>,
+
+	policies:        Arc<[PaperPolicy]>,
+	custom_policies: CustomPoliciesRef,
+	policy_index:    AtomicUsize,
+	is_auto_policy:  AtomicBool,
+
+	auto_policy_history: Mutex<VecDeque<AutoPolicyDecision>>,
 
 	start_time: AtomicU64,
 }
 
+#[derive(Serialize, Deserialize, Default)]
+pub struct StatusCounters {
+	total_hits: u64,
+	total_gets: u64,
+	total_sets: u64,
+	total_dels: u64,
+}
+
 /// This struct holds the basic statistical information about `PaperCache`.
 impl Status {
 	/// Returns the cache's PID.
@@ -121,6 +151,20 @@ impl Status {
 		self.total_dels
 	}
 
+	/// Returns the number of gets which were not recorded by the eviction
+	/// policy because the workers' event queue was full.
+	#[must_use]
+	pub fn dropped_gets(&self) -> u64 {
+		self.dropped_gets
+	}
+
+	/// Returns the number of sets which were rejected by the cache's
+	/// admission policy.
+	#[must_use]
+	pub fn rejected_sets(&self) -> u64 {
+		self.rejected_sets
+	}
+
 	/// Returns the cache's current miss ratio.
 	#[must_use]
 	pub fn miss_ratio(&self) -> f64 {
@@ -150,6 +194,13 @@ impl Status {
 		self.is_auto_policy
 	}
 
+	/// Returns the most recent decisions of the auto policy, from the oldest
+	/// to the newest.
+	#[must_use]
+	pub fn auto_policy_history(&self) -> &[AutoPolicyDecision] {
+		&self.auto_policy_history
+	}
+
 	/// Returns the cache's current uptime.
 	#[must_use]
 	pub fn uptime(&self) -> u64 {
@@ -164,6 +215,7 @@ impl AtomicStatus {
 		max_size: CacheSize,
 		policies: &[PaperPolicy],
 		mut policy: PaperPolicy,
+		custom_policies: CustomPoliciesRef,
 	) -> Result<Self, CacheError> {
 		let policies: Arc<[PaperPolicy]> = policies.into();
 		let is_auto_policy = policy.is_auto();
@@ -184,10 +236,18 @@ impl AtomicStatus {
 			total_sets: AtomicU64::default(),
 			total_dels: AtomicU64::default(),
 
+			dropped_gets: AtomicU64::default(),
+			rejected_sets: AtomicU64::default(),
+
+			last_eviction: Mutex::default(),
+
 			policies,
+			custom_policies,
 			policy_index: AtomicUsize::new(policy_index),
 			is_auto_policy: AtomicBool::new(is_auto_policy),
 
+			auto_policy_history: Mutex::new(VecDeque::with_capacity(AUTO_POLICY_HISTORY_SIZE)),
+
 			start_time: AtomicU64::new(time::timestamp()),
 		};
 
@@ -203,11 +263,27 @@ impl AtomicStatus {
 	pub fn used_size(&self, policy: &PaperPolicy) -> CacheSize {
 		let base_used_size = self.base_used_size.load(Ordering::Acquire);
 		let num_objects = self.num_objects.load(Ordering::Acquire);
-		let policy_overhead = get_policy_overhead(policy);
+		let policy_overhead = self.policy_overhead(policy);
 
 		base_used_size + num_objects * policy_overhead as CacheSize
 	}
 
+	/// Returns the per-object overhead of the supplied policy.
+	#[must_use]
+	pub fn policy_overhead(&self, policy: &PaperPolicy) -> ObjectSize {
+		self.custom_policies.policy_overhead(policy)
+	}
+
+	#[must_use]
+	pub fn custom_policies(&self) -> &CustomPoliciesRef {
+		&self.custom_policies
+	}
+
+	#[must_use]
+	pub fn num_objects(&self) -> u64 {
+		self.num_objects.load(Ordering::Acquire)
+	}
+
 	#[must_use]
 	pub fn policies(&self) -> &[PaperPolicy] {
 		&self.policies
@@ -225,20 +301,53 @@ impl AtomicStatus {
 	}
 
 	pub fn incr_hits(&self) {
-		self.total_gets.fetch_add(1, Ordering::Relaxed);
-		self.total_hits.fetch_add(1, Ordering::Relaxed);
+		self.incr_hits_by(1);
+	}
+
+	pub fn incr_hits_by(&self, count: u64) {
+		self.total_gets.fetch_add(count, Ordering::Relaxed);
+		self.total_hits.fetch_add(count, Ordering::Relaxed);
 	}
 
 	pub fn incr_misses(&self) {
-		self.total_gets.fetch_add(1, Ordering::Relaxed);
+		self.incr_misses_by(1);
+	}
+
+	pub fn incr_misses_by(&self, count: u64) {
+		self.total_gets.fetch_add(count, Ordering::Relaxed);
 	}
 
 	pub fn incr_sets(&self) {
-		self.total_sets.fetch_add(1, Ordering::Relaxed);
+		self.incr_sets_by(1);
+	}
+
+	pub fn incr_sets_by(&self, count: u64) {
+		self.total_sets.fetch_add(count, Ordering::Relaxed);
 	}
 
 	pub fn incr_dels(&self) {
-		self.total_dels.fetch_add(1, Ordering::Relaxed);
+		self.incr_dels_by(1);
+	}
+
+	pub fn incr_dels_by(&self, count: u64) {
+		self.total_dels.fetch_add(count, Ordering::Relaxed);
+	}
+
+	pub fn incr_dropped_gets_by(&self, count: u64) {
+		self.dropped_gets.fetch_add(count, Ordering::Relaxed);
+	}
+
+	pub fn incr_rejected_sets(&self) {
+		self.rejected_sets.fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[must_use]
+	pub fn last_eviction(&self) -> Option<HashedKey> {
+		*self.last_eviction.lock()
+	}
+
+	pub fn set_last_eviction(&self, key: HashedKey) {
+		*self.last_eviction.lock() = Some(key);
 	}
 
 	pub fn set_max_size(&self, max_size: u64) {
@@ -292,11 +401,51 @@ impl AtomicStatus {
 		Ok(())
 	}
 
+	pub fn record_auto_policy_decision(&self, decision: AutoPolicyDecision) {
+		let mut history = self.auto_policy_history.lock();
+
+		if history.len() == AUTO_POLICY_HISTORY_SIZE {
+			history.pop_front();
+		}
+
+		history.push_back(decision);
+	}
+
+	#[must_use]
+	pub fn auto_policy_history(&self) -> Vec<AutoPolicyDecision> {
+		self.auto_policy_history
+			.lock()
+			.iter()
+			.cloned()
+			.collect()
+	}
+
 	#[must_use]
 	pub fn exceeds_max_size(&self, size: impl AsPrimitive<u64>) -> bool {
 		size.as_() > self.max_size.load(Ordering::Relaxed)
 	}
 
+	#[must_use]
+	pub fn counters(&self) -> StatusCounters {
+		StatusCounters {
+			total_hits: self.total_hits.load(Ordering::Relaxed),
+			total_gets: self.total_gets.load(Ordering::Relaxed),
+			total_sets: self.total_sets.load(Ordering::Relaxed),
+			total_dels: self.total_dels.load(Ordering::Relaxed),
+		}
+	}
+
+	pub fn restore_counters(&self, counters: &StatusCounters) {
+		self.total_hits
+			.store(counters.total_hits, Ordering::Relaxed);
+		self.total_gets
+			.store(counters.total_gets, Ordering::Relaxed);
+		self.total_sets
+			.store(counters.total_sets, Ordering::Relaxed);
+		self.total_dels
+			.store(counters.total_dels, Ordering::Relaxed);
+	}
+
 	pub fn clear(&self) {
 		self.base_used_size.store(0, Ordering::Release);
 		self.num_objects.store(0, Ordering::Release);
@@ -305,6 +454,9 @@ impl AtomicStatus {
 		self.total_gets.store(0, Ordering::Relaxed);
 		self.total_sets.store(0, Ordering::Relaxed);
 		self.total_dels.store(0, Ordering::Relaxed);
+
+		self.dropped_gets.store(0, Ordering::Relaxed);
+		self.rejected_sets.store(0, Ordering::Relaxed);
 	}
 
 	pub fn try_to_status(&self) -> Result<Status, CacheError> {
@@ -335,10 +487,15 @@ impl AtomicStatus {
 			total_sets: self.total_sets.load(Ordering::Relaxed),
 			total_dels: self.total_dels.load(Ordering::Relaxed),
 
+			dropped_gets: self.dropped_gets.load(Ordering::Relaxed),
+			rejected_sets: self.rejected_sets.load(Ordering::Relaxed),
+
 			policies: self.policies.clone(),
 			policy: self.policies[self.policy_index.load(Ordering::Relaxed)],
 			is_auto_policy: self.is_auto_policy.load(Ordering::Relaxed),
 
+			auto_policy_history: self.auto_policy_history().into(),
+
 			start_time: self.start_time.load(Ordering::Relaxed),
 		};
 
@@ -363,14 +520,22 @@ fn get_policy_index(policies: &[PaperPolicy], policy: PaperPolicy) -> Result<usi
 
 #[cfg(test)]
 mod tests {
-	use std::sync::atomic::Ordering;
+	use std::sync::{Arc, atomic::Ordering};
 
-	use crate::{PaperPolicy, status::AtomicStatus};
+	use crate::{
+		PaperPolicy,
+		policy::{AutoPolicyDecision, AutoPolicyReason},
+		status::{AUTO_POLICY_HISTORY_SIZE, AtomicStatus},
+	};
 
 	#[test]
 	fn it_clears_atomic_status() {
-		let status = AtomicStatus::new(1000, &[PaperPolicy::Lfu], PaperPolicy::Lfu)
-			.expect("Could not initialize atomic status");
+		let status = AtomicStatus::new(
+			1000,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Lfu,
+			Arc::default(),
+		).expect("Could not initialize atomic status");
 
 		status.update_base_used_size(1);
 		status.incr_num_objects();
@@ -394,4 +559,30 @@ mod tests {
 		assert_eq!(status.total_sets.load(Ordering::Relaxed), 0);
 		assert_eq!(status.total_dels.load(Ordering::Relaxed), 0);
 	}
+
+	#[test]
+	fn it_bounds_the_auto_policy_history() {
+		let status = AtomicStatus::new(
+			1000,
+			&[PaperPolicy::Lfu],
+			PaperPolicy::Auto,
+			Arc::default(),
+		).expect("Could not initialize atomic status");
+
+		for timestamp in 0..AUTO_POLICY_HISTORY_SIZE as u64 + 10 {
+			status.record_auto_policy_decision(AutoPolicyDecision {
+				timestamp,
+				miss_ratios: Box::new([(PaperPolicy::Lfu, 1.0)]),
+				num_samples: 0,
+				current_policy: PaperPolicy::Lfu,
+				chosen_policy: PaperPolicy::Lfu,
+				reason: AutoPolicyReason::NoSamples,
+			});
+		}
+
+		let history = status.auto_policy_history();
+
+		assert_eq!(history.len(), AUTO_POLICY_HISTORY_SIZE);
+		assert_eq!(history[0].timestamp(), 10);
+	}
 }
@@ -5,39 +5,63 @@
  * LICENSE file in the root directory of this source tree.
  */
 
-use std::sync::Arc;
-
-use crossbeam_channel::unbounded;
-use log::error;
+use crossbeam_channel::{bounded, select};
 use typesize::TypeSize;
 
 use crate::{
 	ObjectMapRef,
 	OverheadManagerRef,
+	RemovalListenerRef,
 	StatusRef,
 	error::CacheError,
-	worker::{PolicyWorker, TtlWorker, Worker, WorkerReceiver, WorkerSender, register_worker},
+	policy::AutoPolicyConfig,
+	worker::{
+		PolicyWorker,
+		ShutdownReceiver,
+		TtlWorker,
+		Worker,
+		WorkerEvent,
+		WorkerHandle,
+		WorkerReceiver,
+		WorkerSender,
+		register_worker,
+		send_event,
+	},
 };
 
 pub struct WorkerManager {
 	listener: WorkerReceiver,
-	workers:  Arc<Box<[WorkerSender]>>,
+	status:   StatusRef,
+
+	policy_worker: WorkerSender,
+	ttl_worker:    WorkerSender,
+	handles:       Vec<WorkerHandle>,
 }
 
 impl Worker for WorkerManager {
-	fn run(&mut self) -> Result<(), CacheError> {
+	fn run(&mut self, shutdown: &ShutdownReceiver) -> Result<(), CacheError> {
 		loop {
-			let Ok(event) = self.listener.recv() else {
-				return Ok(());
-			};
-
-			for worker in self.workers.iter() {
-				if let Err(err) = worker.try_send(event.clone()) {
-					error!("Could not send event to worker: {err:?}");
-					return Err(CacheError::Internal);
-				}
+			select! {
+				recv(self.listener) -> event => match event {
+					Ok(event) => self.forward(event)?,
+					Err(_) => break,
+				},
+
+				recv(shutdown) -> _ => break,
 			}
 		}
+
+		// forward any events which were sent before the shutdown so that the
+		// workers can apply them before they stop
+		for event in self.listener.try_iter() {
+			self.forward(event)?;
+		}
+
+		for handle in self.handles.drain(..) {
+			handle.shutdown()?;
+		}
+
+		Ok(())
 	}
 }
 
@@ -47,37 +71,56 @@ impl WorkerManager {
 		objects: &ObjectMapRef<K, V>,
 		status: &StatusRef,
 		overhead_manager: &OverheadManagerRef,
+		removal_listener: &RemovalListenerRef<K, V>,
+		capacity: usize,
+		auto_policy_config: AutoPolicyConfig,
 	) -> Result<Self, CacheError>
 	where
 		K: 'static + Eq + TypeSize,
 		V: 'static + TypeSize,
 	{
-		let (policy_worker, policy_listener) = unbounded();
-		let (ttl_worker, ttl_listener) = unbounded();
+		let (policy_worker, policy_listener) = bounded(capacity);
+		let (ttl_worker, ttl_listener) = bounded(capacity);
 
-		register_worker(PolicyWorker::<K, V>::new(
+		let policy_handle = register_worker(PolicyWorker::<K, V>::new(
 			policy_listener,
 			objects.clone(),
 			status.clone(),
 			overhead_manager.clone(),
+			removal_listener.clone(),
+			auto_policy_config,
 		)?);
 
-		register_worker(TtlWorker::<K, V>::new(
+		let ttl_handle = register_worker(TtlWorker::<K, V>::new(
 			ttl_listener,
 			objects.clone(),
 			status.clone(),
 			overhead_manager.clone(),
+			removal_listener.clone(),
 		));
 
-		let workers: Arc<Box<[WorkerSender]>> = Arc::new(Box::new([policy_worker, ttl_worker]));
-
 		let manager = WorkerManager {
 			listener,
-			workers,
+			status: status.clone(),
+
+			policy_worker,
+			ttl_worker,
+			handles: vec![policy_handle, ttl_handle],
 		};
 
 		Ok(manager)
 	}
+
+	fn forward(&self, event: WorkerEvent) -> Result<(), CacheError> {
+		// the TTL worker only tracks expiries, so it is not sent accesses or
+		// any of the policy worker's requests, which would otherwise take up
+		// room in its bounded queue
+		if let Some(ttl_event) = event.to_ttl_event() {
+			send_event(&self.ttl_worker, ttl_event, &self.status)?;
+		}
+
+		send_event(&self.policy_worker, event, &self.status)
+	}
 }
 
 unsafe impl Send for WorkerManager {}
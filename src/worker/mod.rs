@@ -9,21 +9,56 @@ mod manager;
 mod policy;
 mod ttl;
 
-use std::thread;
+use std::{
+	sync::Arc,
+	thread::{self, JoinHandle},
+	time::Duration,
+};
 
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Select, Sender, TrySendError, bounded};
+use log::error;
 
 use crate::{
 	CacheSize,
 	HashedKey,
+	StatusRef,
 	error::CacheError,
-	object::{ExpireTime, ObjectSize},
-	policy::PaperPolicy,
+	object::{ExpireTime, ObjectHints, ObjectSize},
+	policy::{AutoPolicyConfig, PaperPolicy},
+	report::PolicyReport,
 };
 
 pub type WorkerSender = Sender<WorkerEvent>;
 pub type WorkerReceiver = Receiver<WorkerEvent>;
 
+pub type WorkerCallback = Arc<dyn Fn() + Send + Sync>;
+
+/// Configures how operations behave when the workers' event queue is full.
+/// Events which only record accesses (i.e., gets) are never waited for, and
+/// are instead dropped and counted in the cache's status.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Backpressure {
+	/// Block the operation until the queue has room for its event.
+	#[default]
+	Block,
+
+	/// Return a [`CacheError::QueueFull`] without applying the operation.
+	///
+	/// This is best-effort: the queue is checked before the operation is
+	/// applied, so concurrent operations which all find room may still
+	/// block briefly until the queue has room for their events.
+	FailFast,
+}
+
+// nothing is ever sent through the shutdown channel; dropping its sender
+// signals the worker to shut down
+pub type ShutdownReceiver = Receiver<()>;
+
+pub struct WorkerHandle {
+	shutdown: Sender<()>,
+	thread:   JoinHandle<Result<(), CacheError>>,
+}
+
 #[derive(Clone)]
 pub enum WorkerEvent {
 	Get(HashedKey, bool),
@@ -31,6 +66,7 @@ pub enum WorkerEvent {
 		HashedKey,
 		ObjectSize,
 		ExpireTime,
+		ObjectHints,
 		Option<(ObjectSize, ExpireTime)>,
 	),
 	Del(HashedKey, ExpireTime),
@@ -41,17 +77,180 @@ pub enum WorkerEvent {
 
 	Resize(CacheSize),
 	Policy(PaperPolicy),
+	AutoPolicy(AutoPolicyConfig),
+
+	Batch(Arc<[WorkerEvent]>),
+
+	// invoked once all preceding events have been fully applied, including
+	// any policy switches and resizes
+	Settle(WorkerCallback),
+
+	// requests the keys of the current policy ordered from the first to the
+	// last to be evicted
+	EvictionOrder(Sender<Vec<HashedKey>>),
+
+	// requests the estimated performance of each of the mini stacks
+	PolicyReport(Sender<PolicyReport>),
+
+	// evicts objects until the used size is at most the supplied size,
+	// replying once the evictions have been applied
+	Evict(CacheSize, Sender<()>),
+}
+
+impl WorkerEvent {
+	/// Returns `true` if the event only records accesses, in which case it
+	/// can be dropped when the workers are overloaded.
+	pub fn is_lossy(&self) -> bool {
+		match self {
+			WorkerEvent::Get(_, _) => true,
+			WorkerEvent::Batch(events) => events.iter().all(WorkerEvent::is_lossy),
+
+			_ => false,
+		}
+	}
+
+	/// Returns `true` if the event affects the objects' expiries, in which
+	/// case it must be applied by the TTL worker.
+	pub fn is_ttl_event(&self) -> bool {
+		match self {
+			WorkerEvent::Set(..)
+			| WorkerEvent::Del(..)
+			| WorkerEvent::Ttl(..)
+			| WorkerEvent::Wipe => true,
+
+			WorkerEvent::Batch(events) => events.iter().any(WorkerEvent::is_ttl_event),
+
+			_ => false,
+		}
+	}
+
+	/// Returns the event with only its expiry-related parts, or `None` if
+	/// the event does not affect the objects' expiries.
+	pub fn to_ttl_event(&self) -> Option<WorkerEvent> {
+		match self {
+			WorkerEvent::Batch(events) if !events.iter().all(WorkerEvent::is_ttl_event) => {
+				let events = events
+					.iter()
+					.filter(|event| event.is_ttl_event())
+					.cloned()
+					.collect::<Arc<[WorkerEvent]>>();
+
+				(!events.is_empty()).then_some(WorkerEvent::Batch(events))
+			},
+
+			event => event.is_ttl_event().then(|| event.clone()),
+		}
+	}
+
+	/// Returns the number of accesses recorded by the event.
+	pub fn num_accesses(&self) -> u64 {
+		match self {
+			WorkerEvent::Get(_, _) => 1,
+			WorkerEvent::Batch(events) => events.iter().map(WorkerEvent::num_accesses).sum(),
+
+			_ => 0,
+		}
+	}
+
+	/// Appends the event to the supplied events, unpacking any batched
+	/// events into their individual events.
+	pub fn flatten_into(self, events: &mut Vec<WorkerEvent>) {
+		match self {
+			WorkerEvent::Batch(batch) => events.extend(batch.iter().cloned()),
+			event => events.push(event),
+		}
+	}
 }
 
 pub trait Worker
 where
 	Self: 'static + Send,
 {
-	fn run(&mut self) -> Result<(), CacheError>;
+	/// Runs the worker until the supplied shutdown receiver is disconnected,
+	/// after which any pending events are applied before returning.
+	fn run(&mut self, shutdown: &ShutdownReceiver) -> Result<(), CacheError>;
+}
+
+impl WorkerHandle {
+	/// Signals the worker to shut down and waits for it to finish.
+	pub fn shutdown(self) -> Result<(), CacheError> {
+		drop(self.shutdown);
+
+		match self.thread.join() {
+			Ok(result) => result,
+
+			Err(err) => {
+				error!("Worker thread panicked: {err:?}");
+				Err(CacheError::Internal)
+			},
+		}
+	}
+}
+
+pub fn register_worker(mut worker: impl Worker) -> WorkerHandle {
+	let (shutdown, shutdown_listener) = bounded(0);
+	let thread = thread::spawn(move || worker.run(&shutdown_listener));
+
+	WorkerHandle {
+		shutdown,
+		thread,
+	}
 }
 
-pub fn register_worker(mut worker: impl Worker) {
-	thread::spawn(move || worker.run());
+/// Sleeps for the supplied delay, waking early and returning `true` if the
+/// worker was signalled to shut down.
+pub fn sleep_or_shutdown(shutdown: &ShutdownReceiver, delay: Duration) -> bool {
+	!matches!(shutdown.recv_timeout(delay), Err(RecvTimeoutError::Timeout))
 }
 
-pub use crate::worker::{manager::WorkerManager, policy::PolicyWorker, ttl::TtlWorker};
+/// Sleeps for up to the supplied delay, waking early if an event arrives
+/// (without receiving it) so that the bounded event queues are drained
+/// promptly. Returns `true` if the worker was signalled to shut down.
+pub fn wait_for_events<T>(
+	listener: &Receiver<T>,
+	shutdown: &ShutdownReceiver,
+	delay: Duration,
+) -> bool {
+	let mut select = Select::new();
+
+	select.recv(listener);
+	let shutdown_index = select.recv(shutdown);
+
+	select
+		.ready_timeout(delay)
+		.is_ok_and(|index| index == shutdown_index)
+}
+
+/// Sends the event, dropping and counting it if it is lossy and the channel
+/// is full. All other events block until the channel has room for them.
+pub fn send_event(
+	sender: &WorkerSender,
+	event: WorkerEvent,
+	status: &StatusRef,
+) -> Result<(), CacheError> {
+	if !event.is_lossy() {
+		return sender.send(event).map_err(|err| {
+			error!("Could not send event to worker: {err:?}");
+			CacheError::Internal
+		});
+	}
+
+	match sender.try_send(event) {
+		Ok(()) => Ok(()),
+
+		Err(TrySendError::Full(event)) => {
+			status.incr_dropped_gets_by(event.num_accesses());
+			Ok(())
+		},
+
+		// the workers only disconnect once the cache is shut down, after
+		// which the accesses no longer need to be recorded
+		Err(TrySendError::Disconnected(_)) => Ok(()),
+	}
+}
+
+pub use crate::worker::{
+	manager::WorkerManager,
+	policy::{PolicyStack, PolicyWorker},
+	ttl::TtlWorker,
+};
@@ -5,34 +5,64 @@
  * LICENSE file in the root directory of this source tree.
  */
 
-use std::{cmp, io};
+use std::{
+	cmp,
+	io,
+	iter::Peekable,
+	sync::LazyLock,
+	time::{Duration, Instant},
+};
 
 use kwik::file::binary::{ReadChunk, SizedChunk, WriteChunk};
 
-use crate::{CacheSize, HashedKey, object::ObjectSize, worker::WorkerEvent};
+use crate::{
+	CacheSize,
+	HashedKey,
+	object::{ExpireTime, ObjectCost, ObjectHints, ObjectSize},
+	worker::WorkerEvent,
+};
+
+// the expiries are written to the trace in milliseconds relative to this
+// instant, which (like the trace fragments) lives as long as the process
+static TRACE_EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
 
 #[derive(Clone)]
 pub enum StackEvent {
 	Get(HashedKey),
-	Set(HashedKey, ObjectSize),
+	Set(HashedKey, ObjectSize, ExpireTime, ObjectHints),
 	Del(HashedKey),
+	Ttl(HashedKey, ExpireTime),
 	Wipe,
 	Resize(CacheSize),
 }
 
 pub enum TraceEvent {
+	Get(HashedKey),
+	Set(HashedKey, ObjectSize, ExpireTime, ObjectHints),
+	Del(HashedKey),
+	Ttl(HashedKey, ExpireTime),
+	Resize(CacheSize),
+}
+
+// the fixed-size records in which the trace events are written, where the
+// expiry and hints records belong to the set or TTL record which precedes them
+pub enum TraceRecord {
 	Get(HashedKey),
 	Set(HashedKey, ObjectSize),
 	Del(HashedKey),
+	Ttl(HashedKey),
 	Resize(CacheSize),
+	Expiry(u64),
+	Hints(ObjectHints),
 }
 
 impl StackEvent {
 	pub fn maybe_from_worker_event(worker_event: &WorkerEvent) -> Option<Self> {
 		let event = match worker_event {
 			WorkerEvent::Get(key, hit) if *hit => StackEvent::Get(*key),
-			WorkerEvent::Set(key, size, _, _) => StackEvent::Set(*key, *size),
+			WorkerEvent::Set(key, size, expiry, hints, _) => StackEvent::Set(*key, *size, *expiry, *hints),
 			WorkerEvent::Del(key, _) => StackEvent::Del(*key),
+			WorkerEvent::Ttl(key, _, expiry) => StackEvent::Ttl(*key, *expiry),
 			WorkerEvent::Wipe => StackEvent::Wipe,
 			WorkerEvent::Resize(size) => StackEvent::Resize(*size),
 
@@ -47,8 +77,9 @@ impl TraceEvent {
 	pub fn maybe_from_stack_event(stack_event: &StackEvent) -> Option<Self> {
 		let event = match stack_event {
 			StackEvent::Get(key) => TraceEvent::Get(*key),
-			StackEvent::Set(key, size) => TraceEvent::Set(*key, *size),
+			StackEvent::Set(key, size, expiry, hints) => TraceEvent::Set(*key, *size, *expiry, *hints),
 			StackEvent::Del(key) => TraceEvent::Del(*key),
+			StackEvent::Ttl(key, expiry) => TraceEvent::Ttl(*key, *expiry),
 			StackEvent::Resize(size) => TraceEvent::Resize(*size),
 
 			_ => return None,
@@ -56,94 +87,319 @@ impl TraceEvent {
 
 		Some(event)
 	}
+
+	/// Returns the records which encode the event in a trace fragment. The
+	/// expiry and hints of a set are only written (as separate records) if
+	/// the object has an expiry or non-default hints, so that the common
+	/// events stay small.
+	pub fn records(&self) -> impl Iterator<Item = TraceRecord> {
+		let records = match *self {
+			TraceEvent::Get(key) => [Some(TraceRecord::Get(key)), None, None],
+
+			TraceEvent::Set(key, size, expiry, hints) => [
+				Some(TraceRecord::Set(key, size)),
+				expiry.map(|_| TraceRecord::Expiry(expiry_to_millis(expiry))),
+				(hints != ObjectHints::default()).then_some(TraceRecord::Hints(hints)),
+			],
+
+			TraceEvent::Del(key) => [Some(TraceRecord::Del(key)), None, None],
+
+			TraceEvent::Ttl(key, expiry) => [
+				Some(TraceRecord::Ttl(key)),
+				Some(TraceRecord::Expiry(expiry_to_millis(expiry))),
+				None,
+			],
+
+			TraceEvent::Resize(size) => [Some(TraceRecord::Resize(size)), None, None],
+		};
+
+		records.into_iter().flatten()
+	}
+}
+
+/// Assembles the trace events from the records of a trace fragment.
+pub struct TraceEvents<I>
+where
+	I: Iterator<Item = TraceRecord>,
+{
+	records: Peekable<I>,
 }
 
-impl SizedChunk for TraceEvent {
+impl<I> TraceEvents<I>
+where
+	I: Iterator<Item = TraceRecord>,
+{
+	pub fn new(records: I) -> Self {
+		TraceEvents {
+			records: records.peekable(),
+		}
+	}
+
+	fn next_expiry(&mut self) -> ExpireTime {
+		match self
+			.records
+			.next_if(|record| matches!(record, TraceRecord::Expiry(_)))
+		{
+			Some(TraceRecord::Expiry(millis)) => expiry_from_millis(millis),
+			_ => None,
+		}
+	}
+
+	fn next_hints(&mut self) -> ObjectHints {
+		match self
+			.records
+			.next_if(|record| matches!(record, TraceRecord::Hints(_)))
+		{
+			Some(TraceRecord::Hints(hints)) => hints,
+			_ => ObjectHints::default(),
+		}
+	}
+}
+
+impl<I> Iterator for TraceEvents<I>
+where
+	I: Iterator<Item = TraceRecord>,
+{
+	type Item = TraceEvent;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let event = match self.records.next()? {
+				TraceRecord::Get(key) => TraceEvent::Get(key),
+
+				TraceRecord::Set(key, size) => {
+					let expiry = self.next_expiry();
+					let hints = self.next_hints();
+
+					TraceEvent::Set(key, size, expiry, hints)
+				},
+
+				TraceRecord::Del(key) => TraceEvent::Del(key),
+				TraceRecord::Ttl(key) => TraceEvent::Ttl(key, self.next_expiry()),
+				TraceRecord::Resize(size) => TraceEvent::Resize(size),
+
+				// the expiry and hints are always consumed with the record
+				// which precedes them, so a stray one is skipped
+				TraceRecord::Expiry(_) | TraceRecord::Hints(_) => continue,
+			};
+
+			return Some(event);
+		}
+	}
+}
+
+impl SizedChunk for TraceRecord {
 	fn chunk_size() -> usize {
 		let set_size = HashedKey::chunk_size() + ObjectSize::chunk_size() + 1;
 		let resize_size = CacheSize::chunk_size() + 1;
+		let expiry_size = u64::chunk_size() + 1;
+		let hints_size = ObjectCost::chunk_size() + u8::chunk_size() + 1;
 
-		cmp::max(set_size, resize_size)
+		cmp::max(
+			cmp::max(set_size, resize_size),
+			cmp::max(expiry_size, hints_size),
+		)
 	}
 }
 
-impl ReadChunk for TraceEvent {
+impl ReadChunk for TraceRecord {
 	fn from_chunk(buf: &[u8]) -> std::io::Result<Self> {
-		let event = match buf[0] {
-			EventByte::GET => {
+		let record = match buf[0] {
+			RecordByte::GET => {
 				let key = HashedKey::from_chunk(&buf[1..HashedKey::chunk_size() + 1])?;
-				TraceEvent::Get(key)
+				TraceRecord::Get(key)
 			},
 
-			EventByte::SET => {
-				let key = HashedKey::from_chunk(&buf[1..HashedKey::chunk_size() + 1])?;
-				let size = ObjectSize::from_chunk(&buf[HashedKey::chunk_size() + 1..])?;
-				TraceEvent::Set(key, size)
+			RecordByte::SET => {
+				let size_offset = HashedKey::chunk_size() + 1;
+				let end_offset = size_offset + ObjectSize::chunk_size();
+
+				let key = HashedKey::from_chunk(&buf[1..size_offset])?;
+				let size = ObjectSize::from_chunk(&buf[size_offset..end_offset])?;
+
+				TraceRecord::Set(key, size)
 			},
 
-			EventByte::DEL => {
+			RecordByte::DEL => {
 				let key = HashedKey::from_chunk(&buf[1..HashedKey::chunk_size() + 1])?;
-				TraceEvent::Del(key)
+				TraceRecord::Del(key)
 			},
 
-			EventByte::RESIZE => {
+			RecordByte::RESIZE => {
 				let size = CacheSize::from_chunk(&buf[1..CacheSize::chunk_size() + 1])?;
-				TraceEvent::Resize(size)
+				TraceRecord::Resize(size)
+			},
+
+			RecordByte::TTL => {
+				let key = HashedKey::from_chunk(&buf[1..HashedKey::chunk_size() + 1])?;
+				TraceRecord::Ttl(key)
+			},
+
+			RecordByte::EXPIRY => {
+				let millis = u64::from_chunk(&buf[1..u64::chunk_size() + 1])?;
+				TraceRecord::Expiry(millis)
+			},
+
+			RecordByte::HINTS => {
+				let priority_offset = ObjectCost::chunk_size() + 1;
+
+				let cost = ObjectCost::from_chunk(&buf[1..priority_offset])?;
+				let priority = u8::from_chunk(&buf[priority_offset..priority_offset + u8::chunk_size()])?;
+
+				TraceRecord::Hints(ObjectHints {
+					cost,
+					priority,
+				})
 			},
 
 			_ => unreachable!(),
 		};
 
-		Ok(event)
+		Ok(record)
 	}
 }
 
-impl WriteChunk for TraceEvent {
+impl WriteChunk for TraceRecord {
 	fn as_chunk(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+		let offset = buf.len();
+
 		match self {
-			TraceEvent::Get(key) => {
-				buf.push(EventByte::GET);
+			TraceRecord::Get(key) => {
+				buf.push(RecordByte::GET);
 				key.as_chunk(buf)?;
-
-				let remaining = TraceEvent::chunk_size() - HashedKey::chunk_size() - 1;
-				let zeros = std::iter::repeat_n(0, remaining);
-				buf.extend(zeros);
 			},
 
-			TraceEvent::Set(key, size) => {
-				buf.push(EventByte::SET);
+			TraceRecord::Set(key, size) => {
+				buf.push(RecordByte::SET);
 				key.as_chunk(buf)?;
 				size.as_chunk(buf)?;
 			},
 
-			TraceEvent::Del(key) => {
-				buf.push(EventByte::DEL);
+			TraceRecord::Del(key) => {
+				buf.push(RecordByte::DEL);
 				key.as_chunk(buf)?;
+			},
 
-				let remaining = TraceEvent::chunk_size() - HashedKey::chunk_size() - 1;
-				let zeros = std::iter::repeat_n(0, remaining);
-				buf.extend(zeros);
+			TraceRecord::Ttl(key) => {
+				buf.push(RecordByte::TTL);
+				key.as_chunk(buf)?;
 			},
 
-			TraceEvent::Resize(size) => {
-				buf.push(EventByte::RESIZE);
+			TraceRecord::Resize(size) => {
+				buf.push(RecordByte::RESIZE);
 				size.as_chunk(buf)?;
+			},
+
+			TraceRecord::Expiry(millis) => {
+				buf.push(RecordByte::EXPIRY);
+				millis.as_chunk(buf)?;
+			},
 
-				let remaining = TraceEvent::chunk_size() - CacheSize::chunk_size() - 1;
-				let zeros = std::iter::repeat_n(0, remaining);
-				buf.extend(zeros);
+			TraceRecord::Hints(hints) => {
+				buf.push(RecordByte::HINTS);
+				hints.cost.as_chunk(buf)?;
+				hints.priority.as_chunk(buf)?;
 			},
 		}
 
+		// every record is padded to the same size
+		buf.resize(offset + TraceRecord::chunk_size(), 0);
+
 		Ok(())
 	}
 }
 
-struct EventByte;
+struct RecordByte;
 
-impl EventByte {
+impl RecordByte {
 	const GET: u8 = 0;
 	const SET: u8 = 1;
 	const DEL: u8 = 2;
 	const RESIZE: u8 = 3;
+	const TTL: u8 = 4;
+	const EXPIRY: u8 = 5;
+	const HINTS: u8 = 6;
+}
+
+/// Encodes the expiry as one more than the number of milliseconds since the
+/// trace epoch, or zero if there is no expiry.
+fn expiry_to_millis(expiry: ExpireTime) -> u64 {
+	let Some(expiry) = expiry else {
+		return 0;
+	};
+
+	let millis = expiry
+		.saturating_duration_since(*TRACE_EPOCH)
+		.as_millis();
+
+	u64::try_from(millis)
+		.unwrap_or(u64::MAX - 1)
+		.saturating_add(1)
+}
+
+fn expiry_from_millis(millis: u64) -> ExpireTime {
+	match millis {
+		0 => None,
+		millis => TRACE_EPOCH.checked_add(Duration::from_millis(millis - 1)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn trace_events_are_read_from_their_records() {
+		use std::time::{Duration, Instant};
+
+		use kwik::file::binary::{ReadChunk, SizedChunk, WriteChunk};
+
+		use crate::{
+			object::ObjectHints,
+			worker::policy::event::{TraceEvent, TraceEvents, TraceRecord},
+		};
+
+		let hints = ObjectHints {
+			cost: 5,
+			priority: 2,
+		};
+
+		let expiry = Instant::now() + Duration::from_secs(60);
+
+		let events = [
+			TraceEvent::Get(0),
+			TraceEvent::Set(1, 8, None, ObjectHints::default()),
+			TraceEvent::Set(2, 8, Some(expiry), hints),
+			TraceEvent::Ttl(1, None),
+			TraceEvent::Del(2),
+			TraceEvent::Resize(100),
+		];
+
+		let mut buf = Vec::new();
+
+		for record in events.iter().flat_map(TraceEvent::records) {
+			assert!(record.as_chunk(&mut buf).is_ok());
+		}
+
+		// only the set with an expiry and hints and the TTL have more than
+		// one record
+		assert_eq!(TraceRecord::chunk_size(), 13);
+		assert_eq!(buf.len(), 9 * TraceRecord::chunk_size());
+
+		let records = buf
+			.chunks(TraceRecord::chunk_size())
+			.map(|chunk| TraceRecord::from_chunk(chunk).expect("Could not read trace record"));
+
+		let events = TraceEvents::new(records).collect::<Vec<_>>();
+
+		assert!(matches!(
+			events[..],
+			[
+				TraceEvent::Get(0),
+				TraceEvent::Set(1, 8, None, default_hints),
+				TraceEvent::Set(2, 8, Some(_), set_hints),
+				TraceEvent::Ttl(1, None),
+				TraceEvent::Del(2),
+				TraceEvent::Resize(100),
+			] if default_hints == ObjectHints::default() && set_hints == hints
+		));
+	}
 }
@@ -5,48 +5,122 @@
  * LICENSE file in the root directory of this source tree.
  */
 
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::HashMap};
 
+use kwik::time;
 use rayon::prelude::*;
 
 use crate::{
 	CacheSize,
 	HashedKey,
+	NoHasher,
 	ObjectSize,
-	object::overhead::get_policy_overhead,
-	policy::PaperPolicy,
+	error::CacheError,
+	object::{ExpireTime, ObjectCost, ObjectHints},
+	policy::{
+		AutoPolicyConfig,
+		AutoPolicyDecision,
+		AutoPolicyObjective,
+		AutoPolicyReason,
+		CustomPoliciesRef,
+		PaperPolicy,
+	},
+	report::{PolicyEstimate, PolicyReport, get_confidence_interval},
 	worker::policy::{mini_stack::MiniStack, policy_stack::PolicyStack},
 };
 
 // the sampling modulus must be a power of 2
 const MINI_SAMPLING_MODULUS: u64 = 16_777_216;
-const MINI_SAMPLING_THRESHOLD: u64 = 16_777;
 
 pub struct MiniStackManager {
-	mini_stacks: Box<[MiniStack]>,
-	total_gets:  u64,
+	mini_stacks:     Box<[MiniStack]>,
+	custom_policies: CustomPoliciesRef,
+	config:          AutoPolicyConfig,
+	cache_size:      CacheSize,
+
+	// keys are sampled if their hash modulo the sampling modulus is below
+	// the threshold
+	sampling_threshold: u64,
+
+	// the number of sampled gets since the counters were last cleared, which
+	// (like the mini stacks' hits) is cleared at the end of every epoch so
+	// that the miss ratios only cover the epoch
+	sampled_gets: u64,
+
+	// the sizes and miss costs of the sampled keys, and the total size and
+	// cost of the sampled gets since the counters were last cleared
+	objects:       HashMap<HashedKey, (ObjectSize, ObjectCost), NoHasher>,
+	sampled_bytes: u64,
+	sampled_cost:  u64,
 }
 
 impl MiniStackManager {
-	pub fn new(policies: &[PaperPolicy], cache_size: CacheSize) -> Self {
-		let mini_size = get_mini_stack_size(cache_size);
-
+	pub fn new(
+		policies: &[PaperPolicy],
+		cache_size: CacheSize,
+		config: AutoPolicyConfig,
+		custom_policies: CustomPoliciesRef,
+	) -> Result<Self, CacheError> {
+		let sampling_threshold = get_sampling_threshold(config.sample_rate);
+		let mini_size = get_mini_stack_size(cache_size, sampling_threshold);
+
+		// the no-eviction policy is never automatically selected, and its
+		// mini stack would grow without bound
 		let mini_stacks = policies
 			.iter()
-			.map(|policy| MiniStack::new(*policy, mini_size))
-			.collect::<Box<[_]>>();
+			.filter(|policy| !policy.is_no_eviction())
+			.map(|policy| MiniStack::new(*policy, mini_size, &custom_policies))
+			.collect::<Result<Box<[_]>, _>>()?;
 
-		MiniStackManager {
+		let manager = MiniStackManager {
 			mini_stacks,
-			total_gets: 0,
+			custom_policies,
+			config,
+			cache_size,
+
+			sampling_threshold,
+
+			sampled_gets: 0,
+
+			objects: HashMap::with_hasher(NoHasher::default()),
+			sampled_bytes: 0,
+			sampled_cost: 0,
+		};
+
+		Ok(manager)
+	}
+
+	pub fn configure(&mut self, config: AutoPolicyConfig) {
+		let sampling_threshold = get_sampling_threshold(config.sample_rate);
+		self.config = config;
+
+		if sampling_threshold == self.sampling_threshold {
+			return;
 		}
+
+		// the sampled keys change with the sample rate, so the mini stacks
+		// are cleared and refilled with the newly sampled keys
+		let mini_size = get_mini_stack_size(self.cache_size, sampling_threshold);
+		self.sampling_threshold = sampling_threshold;
+
+		self.mini_stacks.par_iter_mut().for_each(|mini_stack| {
+			mini_stack.clear();
+			mini_stack.resize(mini_size);
+		});
+
+		self.sampled_gets = 0;
+
+		self.objects.clear();
+		self.sampled_bytes = 0;
+		self.sampled_cost = 0;
 	}
 
-	pub fn get_index(&mut self, policy: &PaperPolicy) -> usize {
+	/// Returns the index of the supplied policy's mini stack, or `None` if the
+	/// policy is not simulated (e.g., the no-eviction policy).
+	pub fn get_index(&self, policy: &PaperPolicy) -> Option<usize> {
 		self.mini_stacks
 			.iter()
 			.position(|mini_stack| mini_stack.is_policy(policy))
-			.unwrap_or(0)
 	}
 
 	pub fn get_eviction(&mut self, index: usize) -> Option<HashedKey> {
@@ -54,39 +128,65 @@ impl MiniStackManager {
 	}
 
 	pub fn handle_get(&mut self, key: HashedKey) {
-		self.total_gets += 1;
+		if !should_sample(key, self.sampling_threshold) {
+			return;
+		}
+
+		self.sampled_gets += 1;
+
+		// the size of a key which was never set is unknown, so it does not
+		// count towards the byte miss ratios
+		let (size, cost) = self
+			.objects
+			.get(&key)
+			.copied()
+			.unwrap_or((0, ObjectHints::default().cost));
+
+		self.sampled_bytes += size as u64;
+		self.sampled_cost += cost as u64;
+
+		self.mini_stacks
+			.par_iter_mut()
+			.for_each(|mini_stack| mini_stack.update_with_count(key, cost, size));
+	}
 
-		if !should_sample(key) {
+	pub fn handle_set(&mut self, key: HashedKey, size: ObjectSize, expiry: ExpireTime, hints: ObjectHints) {
+		if !should_sample(key, self.sampling_threshold) {
 			return;
 		}
 
+		self.objects.insert(key, (size, hints.cost));
+
 		self.mini_stacks
 			.par_iter_mut()
-			.for_each(|mini_stack| mini_stack.update_with_count(key));
+			.for_each(|mini_stack| mini_stack.insert_with_hints(key, size, expiry, hints));
 	}
 
-	pub fn handle_set(&mut self, key: HashedKey, size: ObjectSize) {
-		if !should_sample(key) {
+	pub fn handle_ttl(&mut self, key: HashedKey, expiry: ExpireTime) {
+		if !should_sample(key, self.sampling_threshold) {
 			return;
 		}
 
 		self.mini_stacks
 			.par_iter_mut()
-			.for_each(|mini_stack| mini_stack.insert(key, size));
+			.for_each(|mini_stack| mini_stack.update_expiry(key, expiry));
 	}
 
 	pub fn handle_del(&mut self, key: HashedKey) {
-		if !should_sample(key) {
+		if !should_sample(key, self.sampling_threshold) {
 			return;
 		}
 
+		self.objects.remove(&key);
+
 		self.mini_stacks
 			.par_iter_mut()
 			.for_each(|mini_stack| mini_stack.remove(key));
 	}
 
 	pub fn handle_resize(&mut self, size: CacheSize) {
-		let mini_size = get_mini_stack_size(size);
+		let mini_size = get_mini_stack_size(size, self.sampling_threshold);
+		self.cache_size = size;
 
 		self.mini_stacks
 			.par_iter_mut()
@@ -98,7 +198,11 @@ impl MiniStackManager {
 			.par_iter_mut()
 			.for_each(|mini_stack| mini_stack.clear());
 
-		self.total_gets = 0;
+		self.sampled_gets = 0;
+
+		self.objects.clear();
+		self.sampled_bytes = 0;
+		self.sampled_cost = 0;
 	}
 
 	pub fn apply_evictions(&mut self, exclude_index: usize, evictions: Vec<HashedKey>) {
@@ -113,28 +217,28 @@ impl MiniStackManager {
 			});
 	}
 
-	pub fn get_optimal_policy(&mut self, current_policy: &PaperPolicy) -> Option<PaperPolicy> {
-		let sampling_ratio = MINI_SAMPLING_THRESHOLD as f64 / MINI_SAMPLING_MODULUS as f64;
-		let expected_count = self.total_gets as f64 * sampling_ratio;
-
-		let current_miss_ratio = self.mini_stacks.iter().find_map(|mini_stack| {
-			if !mini_stack.is_policy(current_policy) {
-				return None;
-			}
-
-			Some(mini_stack.miss_ratio(expected_count))
-		})?;
+	/// Compares the mini stacks' miss ratios since the previous epoch and
+	/// returns the decision of whether to switch from the current policy.
+	pub fn get_optimal_policy(&mut self, current_policy: &PaperPolicy) -> Option<AutoPolicyDecision> {
+		// the no-eviction policy is not simulated, so the cache is assumed to
+		// miss on every get while it is the current policy (which switches to
+		// any simulated policy that hits at all)
+		let current_miss_ratio = self
+			.mini_stacks
+			.iter()
+			.find(|mini_stack| mini_stack.is_policy(current_policy))
+			.map_or(1.0, |mini_stack| self.objective_miss_ratio(mini_stack));
 
 		let optimal_mini_stack = self.mini_stacks.iter().min_by(|a, b| {
-			match a
-				.miss_ratio(expected_count)
-				.total_cmp(&b.miss_ratio(expected_count))
+			match self
+				.objective_miss_ratio(a)
+				.total_cmp(&self.objective_miss_ratio(b))
 			{
 				Ordering::Equal => {
 					// the two mini stacks have the same miss ratios, so
 					// select the one with the lower memory overhead
-					let a_overhead = get_policy_overhead(&a.policy());
-					let b_overhead = get_policy_overhead(&b.policy());
+					let a_overhead = self.custom_policies.policy_overhead(&a.policy());
+					let b_overhead = self.custom_policies.policy_overhead(&b.policy());
 
 					a_overhead.cmp(&b_overhead)
 				},
@@ -143,14 +247,34 @@ impl MiniStackManager {
 			}
 		})?;
 
-		let optimal_miss_ratio = optimal_mini_stack.miss_ratio(expected_count);
-
-		let maybe_optimal_policy = if optimal_miss_ratio < current_miss_ratio {
-			// make sure we only switch to a different policy that performs better
-			// than the current policy
-			Some(optimal_mini_stack.policy())
+		let optimal_miss_ratio = self.objective_miss_ratio(optimal_mini_stack);
+		let target_miss_ratio = current_miss_ratio * (1.0 - self.config.min_improvement);
+
+		// make sure we only switch to a different policy that performs better
+		// than the current policy by at least the minimum improvement
+		let reason = if self.sampled_gets == 0 {
+			AutoPolicyReason::NoSamples
+		} else if optimal_miss_ratio >= current_miss_ratio {
+			AutoPolicyReason::AlreadyOptimal
+		} else if optimal_miss_ratio >= target_miss_ratio {
+			AutoPolicyReason::BelowMinImprovement
 		} else {
-			None
+			AutoPolicyReason::Switched
+		};
+
+		let miss_ratios = self
+			.mini_stacks
+			.iter()
+			.map(|mini_stack| (mini_stack.policy(), self.objective_miss_ratio(mini_stack)))
+			.collect();
+
+		let decision = AutoPolicyDecision {
+			timestamp: time::timestamp(),
+			miss_ratios,
+			num_samples: self.sampled_gets,
+			current_policy: *current_policy,
+			chosen_policy: optimal_mini_stack.policy(),
+			reason,
 		};
 
 		// clear the miss ratio counters each epoch to only get the miss ratios
@@ -158,22 +282,195 @@ impl MiniStackManager {
 		// make too much of a difference)
 		self.clear_ministack_counters();
 
-		maybe_optimal_policy
+		Some(decision)
+	}
+
+	/// Returns the mini stacks' miss ratios since the counters were last
+	/// cleared (i.e., at the end of the previous epoch if the cache's policy
+	/// is auto) without clearing them.
+	pub fn report(&self) -> PolicyReport {
+		let estimates = self
+			.mini_stacks
+			.iter()
+			.map(|mini_stack| {
+				let miss_ratio = mini_stack.miss_ratio(self.sampled_gets as f64);
+
+				PolicyEstimate {
+					policy: mini_stack.policy(),
+					miss_ratio,
+					miss_ratio_interval: get_confidence_interval(miss_ratio, self.sampled_gets),
+					byte_miss_ratio: mini_stack.byte_miss_ratio(self.sampled_bytes as f64),
+				}
+			})
+			.collect();
+
+		PolicyReport {
+			num_samples: self.sampled_gets,
+			estimates,
+		}
+	}
+
+	/// Returns the mini stack's miss ratio, or the ratio of its miss cost to
+	/// the total cost of the sampled gets, depending on the objective.
+	fn objective_miss_ratio(&self, mini_stack: &MiniStack) -> f64 {
+		match self.config.objective {
+			AutoPolicyObjective::MissRatio => mini_stack.miss_ratio(self.sampled_gets as f64),
+
+			AutoPolicyObjective::MissCost => mini_stack.miss_cost_ratio(self.sampled_cost as f64),
+		}
 	}
 
 	fn clear_ministack_counters(&mut self) {
 		self.mini_stacks
 			.par_iter_mut()
 			.for_each(|mini_stack| mini_stack.clear_counters());
+
+		self.sampled_gets = 0;
+		self.sampled_bytes = 0;
+		self.sampled_cost = 0;
+
+		// the keys which are no longer in any of the mini stacks are dropped
+		// so that the sampled objects do not grow without bound
+		let mini_stacks = &self.mini_stacks;
+
+		self.objects.retain(|key, _| {
+			mini_stacks
+				.iter()
+				.any(|mini_stack| mini_stack.contains(*key))
+		});
 	}
 }
 
-fn should_sample(key: HashedKey) -> bool {
+fn should_sample(key: HashedKey, sampling_threshold: u64) -> bool {
 	// this optimization only works if the sampling modulus is a power of 2
-	key & (MINI_SAMPLING_MODULUS - 1) < MINI_SAMPLING_THRESHOLD
+	key & (MINI_SAMPLING_MODULUS - 1) < sampling_threshold
 }
 
-fn get_mini_stack_size(size: CacheSize) -> CacheSize {
-	let ratio = MINI_SAMPLING_THRESHOLD as f64 / MINI_SAMPLING_MODULUS as f64;
+fn get_sampling_threshold(sample_rate: f64) -> u64 {
+	((sample_rate * MINI_SAMPLING_MODULUS as f64).round() as u64).clamp(1, MINI_SAMPLING_MODULUS)
+}
+
+fn get_mini_stack_size(size: CacheSize, sampling_threshold: u64) -> CacheSize {
+	let ratio = sampling_threshold as f64 / MINI_SAMPLING_MODULUS as f64;
 	(size as f64 * ratio) as u64
 }
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn epoch_miss_ratios_do_not_drift() {
+		use std::sync::Arc;
+
+		use crate::{
+			object::ObjectHints,
+			policy::{AutoPolicyConfig, PaperPolicy},
+			worker::policy::mini_stack::MiniStackManager,
+		};
+
+		let config = AutoPolicyConfig::new().sample_rate(1.0);
+
+		let mut manager = MiniStackManager::new(
+			&[PaperPolicy::Lfu, PaperPolicy::Lru],
+			1000,
+			config,
+			Arc::default(),
+		)
+		.expect("Could not initialize mini stack manager");
+
+		for key in 0..10 {
+			manager.handle_set(key, 1, None, ObjectHints::default());
+		}
+
+		let mut total_gets = 0;
+
+		for epoch in 1..=5 {
+			// every get of the same workload hits in every epoch
+			for key in 0..10 {
+				for _ in 0..10 {
+					manager.handle_get(key);
+				}
+			}
+
+			total_gets += 100;
+
+			let decision = manager
+				.get_optimal_policy(&PaperPolicy::Lru)
+				.expect("Could not compare the mini stacks");
+
+			assert_eq!(decision.num_samples(), 100);
+
+			for (_, miss_ratio) in decision.miss_ratios() {
+				assert_eq!(*miss_ratio, 0.0);
+			}
+
+			// the hits are cleared every epoch, so a miss ratio relative to
+			// the gets since the cache was created (as the ratios once were)
+			// drifts towards 1.0 even though every get hits
+			let hits = decision.num_samples() as f64 * (1.0 - decision.miss_ratios()[0].1);
+			let lifetime_miss_ratio = 1.0 - hits / total_gets as f64;
+
+			assert_eq!(lifetime_miss_ratio, 1.0 - 1.0 / epoch as f64);
+		}
+	}
+
+	#[test]
+	fn it_has_no_index_for_no_eviction() {
+		use std::sync::Arc;
+
+		use crate::{
+			policy::{AutoPolicyConfig, PaperPolicy},
+			worker::policy::mini_stack::MiniStackManager,
+		};
+
+		let manager = MiniStackManager::new(
+			&[PaperPolicy::Lru, PaperPolicy::NoEviction],
+			1000,
+			AutoPolicyConfig::new(),
+			Arc::default(),
+		)
+		.expect("Could not initialize mini stack manager");
+
+		assert_eq!(manager.get_index(&PaperPolicy::Lru), Some(0));
+		assert_eq!(manager.get_index(&PaperPolicy::NoEviction), None);
+	}
+
+	#[test]
+	fn it_switches_away_from_no_eviction() {
+		use std::sync::Arc;
+
+		use crate::{
+			object::ObjectHints,
+			policy::{AutoPolicyConfig, AutoPolicyReason, PaperPolicy},
+			worker::policy::mini_stack::MiniStackManager,
+		};
+
+		let config = AutoPolicyConfig::new().sample_rate(1.0);
+
+		let mut manager = MiniStackManager::new(
+			&[PaperPolicy::Lru, PaperPolicy::NoEviction],
+			1000,
+			config,
+			Arc::default(),
+		)
+		.expect("Could not initialize mini stack manager");
+
+		let decision = manager
+			.get_optimal_policy(&PaperPolicy::NoEviction)
+			.expect("Could not compare the mini stacks");
+
+		assert_eq!(decision.reason(), AutoPolicyReason::NoSamples);
+
+		for key in 0..10 {
+			manager.handle_set(key, 1, None, ObjectHints::default());
+			manager.handle_get(key);
+		}
+
+		let decision = manager
+			.get_optimal_policy(&PaperPolicy::NoEviction)
+			.expect("Could not compare the mini stacks");
+
+		assert_eq!(decision.current_policy(), PaperPolicy::NoEviction);
+		assert_eq!(decision.chosen_policy(), PaperPolicy::Lru);
+		assert_eq!(decision.reason(), AutoPolicyReason::Switched);
+	}
+}
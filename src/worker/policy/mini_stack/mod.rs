@@ -13,8 +13,9 @@ use crate::{
 	CacheSize,
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
-	policy::PaperPolicy,
+	error::CacheError,
+	object::{ExpireTime, ObjectCost, ObjectHints, ObjectSize},
+	policy::{CustomPolicies, PaperPolicy},
 	worker::policy::policy_stack::{PolicyStack, init_policy_stack},
 };
 
@@ -27,13 +28,19 @@ pub struct MiniStack {
 	max_size:  CacheSize,
 	used_size: CacheSize,
 
-	hits: u64,
+	hits:      u64,
+	hit_cost:  u64,
+	hit_bytes: u64,
 }
 
 impl MiniStack {
-	pub fn new(policy: PaperPolicy, size: CacheSize) -> Self {
-		MiniStack {
-			stack: init_policy_stack(policy, size),
+	pub fn new(
+		policy: PaperPolicy,
+		size: CacheSize,
+		custom_policies: &CustomPolicies,
+	) -> Result<Self, CacheError> {
+		let mini_stack = MiniStack {
+			stack: init_policy_stack(policy, size, custom_policies)?,
 			sizes: HashMap::with_hasher(NoHasher::default()),
 
 			policy,
@@ -42,7 +49,11 @@ impl MiniStack {
 			used_size: 0,
 
 			hits: 0,
-		}
+			hit_cost: 0,
+			hit_bytes: 0,
+		};
+
+		Ok(mini_stack)
 	}
 
 	pub fn policy(&self) -> PaperPolicy {
@@ -56,9 +67,25 @@ impl MiniStack {
 		}
 	}
 
-	pub fn update_with_count(&mut self, key: HashedKey) {
+	pub fn miss_cost_ratio(&self, expected_cost: f64) -> f64 {
+		match expected_cost {
+			0.0 => 1.0,
+			cost => (1.0 - self.hit_cost as f64 / cost).clamp(0.0, 1.0),
+		}
+	}
+
+	pub fn byte_miss_ratio(&self, expected_bytes: f64) -> f64 {
+		match expected_bytes {
+			0.0 => 1.0,
+			bytes => (1.0 - self.hit_bytes as f64 / bytes).clamp(0.0, 1.0),
+		}
+	}
+
+	pub fn update_with_count(&mut self, key: HashedKey, cost: ObjectCost, size: ObjectSize) {
 		if self.stack.contains(key) {
 			self.hits += 1;
+			self.hit_cost += cost as u64;
+			self.hit_bytes += size as u64;
 		}
 
 		self.update(key);
@@ -66,6 +93,8 @@ impl MiniStack {
 
 	pub fn clear_counters(&mut self) {
 		self.hits = 0;
+		self.hit_cost = 0;
+		self.hit_bytes = 0;
 	}
 
 	fn reduce(&mut self, target_size: CacheSize) {
@@ -95,21 +124,29 @@ impl PolicyStack for MiniStack {
 		self.stack.contains(key)
 	}
 
-	fn insert(&mut self, key: HashedKey, size: ObjectSize) {
-		self.reduce(self.max_size - size as CacheSize);
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, expiry: ExpireTime) {
+		self.insert_with_hints(key, size, expiry, ObjectHints::default());
+	}
+
+	fn insert_with_hints(&mut self, key: HashedKey, size: ObjectSize, expiry: ExpireTime, hints: ObjectHints) {
+		self.reduce(self.max_size.saturating_sub(size as CacheSize));
 
 		if let Some(old_size) = self.sizes.insert(key, size) {
 			self.used_size -= old_size as CacheSize;
 		}
 
 		self.used_size += size as CacheSize;
-		self.stack.insert(key, size);
+		self.stack.insert_with_hints(key, size, expiry, hints);
 	}
 
 	fn update(&mut self, key: HashedKey) {
 		self.stack.update(key);
 	}
 
+	fn update_expiry(&mut self, key: HashedKey, expiry: ExpireTime) {
+		self.stack.update_expiry(key, expiry);
+	}
+
 	fn remove(&mut self, key: HashedKey) {
 		self.stack.remove(key);
 
@@ -132,6 +169,8 @@ impl PolicyStack for MiniStack {
 		self.used_size = 0;
 
 		self.hits = 0;
+		self.hit_cost = 0;
+		self.hit_bytes = 0;
 	}
 
 	fn evict_one(&mut self) -> Option<HashedKey> {
@@ -154,22 +193,24 @@ mod tests {
 	fn used_size_after_reinsert_is_correct() {
 		use crate::{
 			PaperPolicy,
+			policy::CustomPolicies,
 			worker::policy::{mini_stack::MiniStack, policy_stack::PolicyStack},
 		};
 
-		let mut mini_stack = MiniStack::new(PaperPolicy::Lfu, 100);
+		let mut mini_stack = MiniStack::new(PaperPolicy::Lfu, 100, &CustomPolicies::default())
+			.expect("Could not initialize mini stack");
 		assert_eq!(mini_stack.used_size, 0);
 
-		mini_stack.insert(0, 5);
+		mini_stack.insert(0, 5, None);
 		assert_eq!(mini_stack.used_size, 5);
 
-		mini_stack.insert(1, 2);
+		mini_stack.insert(1, 2, None);
 		assert_eq!(mini_stack.used_size, 7);
 
-		mini_stack.insert(0, 4);
+		mini_stack.insert(0, 4, None);
 		assert_eq!(mini_stack.used_size, 6);
 
-		mini_stack.insert(0, 6);
+		mini_stack.insert(0, 6, None);
 		assert_eq!(mini_stack.used_size, 8);
 	}
 
@@ -177,16 +218,18 @@ mod tests {
 	fn used_size_after_remove_is_correct() {
 		use crate::{
 			PaperPolicy,
+			policy::CustomPolicies,
 			worker::policy::{mini_stack::MiniStack, policy_stack::PolicyStack},
 		};
 
-		let mut mini_stack = MiniStack::new(PaperPolicy::Lfu, 100);
+		let mut mini_stack = MiniStack::new(PaperPolicy::Lfu, 100, &CustomPolicies::default())
+			.expect("Could not initialize mini stack");
 		assert_eq!(mini_stack.used_size, 0);
 
-		mini_stack.insert(0, 5);
+		mini_stack.insert(0, 5, None);
 		assert_eq!(mini_stack.used_size, 5);
 
-		mini_stack.insert(1, 2);
+		mini_stack.insert(1, 2, None);
 		assert_eq!(mini_stack.used_size, 7);
 
 		mini_stack.remove(0);
@@ -14,7 +14,7 @@ use std::{
 	collections::VecDeque,
 	io::{Seek, SeekFrom},
 	sync::Arc,
-	thread,
+	thread::{self, JoinHandle},
 	time::{Duration, Instant},
 };
 
@@ -24,35 +24,41 @@ use log::{error, info, warn};
 use parking_lot::RwLock;
 use typesize::TypeSize;
 
+pub use crate::worker::policy::policy_stack::PolicyStack;
 use crate::{
 	CacheSize,
 	EraseKey,
 	HashedKey,
 	ObjectMapRef,
 	OverheadManagerRef,
+	RemovalCause,
+	RemovalListenerRef,
 	StatusRef,
 	erase,
 	error::CacheError,
-	object::ObjectSize,
-	policy::PaperPolicy,
+	object::{ExpireTime, ObjectHints, ObjectSize},
+	policy::{AutoPolicyConfig, AutoPolicyReason, CustomPolicies, PaperPolicy},
 	worker::{
+		ShutdownReceiver,
 		Worker,
+		WorkerCallback,
 		WorkerEvent,
+		WorkerHandle,
 		WorkerReceiver,
 		policy::{
-			event::{StackEvent, TraceEvent},
+			event::{StackEvent, TraceEvent, TraceEvents},
 			mini_stack::MiniStackManager,
-			policy_stack::{PolicyStack, init_policy_stack},
+			policy_stack::init_policy_stack,
 			trace::{TraceFragment, TraceWorker},
 		},
 		register_worker,
+		wait_for_events,
 	},
 };
 
 // the polling value must be a power of 2
 const RECONSTRUCT_POLICY_POLLING: usize = 1_048_576;
 
-const AUTO_POLICY_DURATION: Duration = Duration::from_secs(3_600);
 const SET_RECENCY_DURATION: Duration = Duration::from_secs(5);
 const SHORT_POLLING_DURATION: Duration = Duration::from_millis(1);
 const LONG_POLLING_DURATION: Duration = Duration::from_secs(1);
@@ -63,18 +69,25 @@ pub struct PolicyWorker<K, V> {
 	objects:          ObjectMapRef<K, V>,
 	status:           StatusRef,
 	overhead_manager: OverheadManagerRef,
+	removal_listener: RemovalListenerRef<K, V>,
 
 	policy_stack: Option<Box<dyn PolicyStack>>,
 
 	trace_fragments: Arc<RwLock<VecDeque<TraceFragment>>>,
 	trace_worker:    Sender<StackEvent>,
+	trace_handle:    Option<WorkerHandle>,
+	reconstructions: Vec<JoinHandle<()>>,
 
 	mini_stack_manager: MiniStackManager,
 	mini_index:         Option<usize>,
 	current_policy:     Arc<RwLock<PaperPolicy>>,
 
-	last_auto_policy_time: Option<Instant>,
-	last_set_time:         Option<Instant>,
+	auto_policy_config:      AutoPolicyConfig,
+	last_auto_policy_time:   Option<Instant>,
+	last_auto_policy_switch: Option<Instant>,
+	last_set_time:           Option<Instant>,
+
+	pending_settles: Vec<WorkerCallback>,
 }
 
 impl<K, V> Worker for PolicyWorker<K, V>
@@ -83,17 +96,19 @@ where
 	K: Eq + TypeSize,
 	V: TypeSize,
 {
-	fn run(&mut self) -> Result<(), CacheError> {
+	fn run(&mut self, shutdown: &ShutdownReceiver) -> Result<(), CacheError> {
 		let (policy_reconstruct_tx, policy_reconstruct_rx) = unbounded::<Box<dyn PolicyStack>>();
 
 		let policy_reconstruct_tx = Arc::new(policy_reconstruct_tx);
 		let mut buffered_events = Vec::<StackEvent>::new();
+		let mut is_shutdown = false;
 
 		loop {
-			let events = self
-				.listener
-				.try_iter()
-				.collect::<Vec<WorkerEvent>>();
+			let mut events = Vec::<WorkerEvent>::new();
+
+			for event in self.listener.try_iter() {
+				event.flatten_into(&mut events);
+			}
 
 			let mut has_current_set = false;
 
@@ -101,12 +116,13 @@ where
 				match event {
 					WorkerEvent::Get(key, _) => self.handle_get(key),
 
-					WorkerEvent::Set(key, size, _, _) => {
-						self.handle_set(key, size);
+					WorkerEvent::Set(key, size, expiry, hints, _) => {
+						self.handle_set(key, size, expiry, hints);
 						has_current_set = true;
 					},
 
 					WorkerEvent::Del(key, _) => self.handle_del(key),
+					WorkerEvent::Ttl(key, _, expiry) => self.handle_ttl(key, expiry),
 					WorkerEvent::Wipe => self.handle_wipe(),
 					WorkerEvent::Resize(max_size) => self.handle_resize(max_size),
 
@@ -114,6 +130,20 @@ where
 						self.handle_policy(policy, policy_reconstruct_tx.clone());
 					},
 
+					WorkerEvent::AutoPolicy(config) => self.handle_auto_policy(config),
+
+					WorkerEvent::Settle(ref callback) => self.pending_settles.push(callback.clone()),
+					WorkerEvent::EvictionOrder(ref sender) => self.handle_eviction_order(sender.clone()),
+
+					WorkerEvent::PolicyReport(ref sender) => {
+						sender.send(self.mini_stack_manager.report()).ok();
+					},
+
+					WorkerEvent::Evict(target_size, ref reply) => {
+						self.apply_evictions(target_size, &mut buffered_events)?;
+						reply.send(()).ok();
+					},
+
 					_ => {},
 				}
 
@@ -131,16 +161,27 @@ where
 
 			self.apply_buffered_events(&buffered_events, &policy_reconstruct_rx);
 			self.flush_buffered_events(&mut buffered_events)?;
-			self.apply_evictions(&mut buffered_events)?;
+			self.apply_evictions(self.status.max_size(), &mut buffered_events)?;
+			self.apply_settles();
+
+			if is_shutdown {
+				return self.shutdown();
+			}
 
 			let now = Instant::now();
 
 			if let Some(policy) = self.perform_auto_policy(now, has_current_set) {
+				// make room for the new policy's overhead before switching so
+				// that the used size never exceeds the cache's maximum size
+				let target_size = self.auto_policy_target_size(policy);
+				self.apply_evictions(target_size, &mut buffered_events)?;
+
 				self.status.set_auto_policy(policy)?;
 				self.handle_policy(policy, policy_reconstruct_tx.clone());
 			}
 
-			self.delay_event_loop(now, has_current_set);
+			let delay = self.event_loop_delay(now, has_current_set);
+			is_shutdown = wait_for_events(&self.listener, shutdown, delay);
 		}
 	}
 }
@@ -155,18 +196,25 @@ where
 		objects: ObjectMapRef<K, V>,
 		status: StatusRef,
 		overhead_manager: OverheadManagerRef,
+		removal_listener: RemovalListenerRef<K, V>,
+		auto_policy_config: AutoPolicyConfig,
 	) -> Result<Self, CacheError> {
 		let max_cache_size = status.max_size();
 
-		let mini_stacks = MiniStackManager::new(status.policies(), max_cache_size);
+		let mini_stacks = MiniStackManager::new(
+			status.policies(),
+			max_cache_size,
+			auto_policy_config,
+			status.custom_policies().clone(),
+		)?;
 
 		let policy = status.policy();
-		let policy_stack = init_policy_stack(policy, max_cache_size);
+		let policy_stack = init_policy_stack(policy, max_cache_size, status.custom_policies())?;
 
 		let trace_fragments = Arc::new(RwLock::new(VecDeque::new()));
 		let (trace_worker, trace_listener) = unbounded();
 
-		register_worker(TraceWorker::new(trace_listener, trace_fragments.clone()));
+		let trace_handle = register_worker(TraceWorker::new(trace_listener, trace_fragments.clone()));
 
 		// we need the initial size so we can accurately reconstruct the
 		// policy stacks after the cache is resized
@@ -181,19 +229,26 @@ where
 			objects,
 			status,
 			overhead_manager,
+			removal_listener,
 
 			policy_stack: Some(policy_stack),
 
 			trace_fragments,
 			trace_worker,
+			trace_handle: Some(trace_handle),
+			reconstructions: Vec::new(),
 
 			mini_stack_manager: mini_stacks,
 			mini_index: None,
 
 			current_policy: Arc::new(RwLock::new(policy)),
 
+			auto_policy_config,
 			last_auto_policy_time: None,
+			last_auto_policy_switch: None,
 			last_set_time: None,
+
+			pending_settles: Vec::new(),
 		};
 
 		Ok(worker)
@@ -207,12 +262,12 @@ where
 		self.mini_stack_manager.handle_get(key);
 	}
 
-	fn handle_set(&mut self, key: HashedKey, size: ObjectSize) {
+	fn handle_set(&mut self, key: HashedKey, size: ObjectSize, expiry: ExpireTime, hints: ObjectHints) {
 		if let Some(stack) = &mut self.policy_stack {
-			stack.insert(key, size);
+			stack.insert_with_hints(key, size, expiry, hints);
 		}
 
-		self.mini_stack_manager.handle_set(key, size);
+		self.mini_stack_manager.handle_set(key, size, expiry, hints);
 	}
 
 	fn handle_del(&mut self, key: HashedKey) {
@@ -223,6 +278,14 @@ where
 		self.mini_stack_manager.handle_del(key);
 	}
 
+	fn handle_ttl(&mut self, key: HashedKey, expiry: ExpireTime) {
+		if let Some(stack) = &mut self.policy_stack {
+			stack.update_expiry(key, expiry);
+		}
+
+		self.mini_stack_manager.handle_ttl(key, expiry);
+	}
+
 	fn handle_resize(&mut self, size: CacheSize) {
 		if let Some(stack) = &mut self.policy_stack {
 			stack.resize(size);
@@ -231,6 +294,13 @@ where
 		self.mini_stack_manager.handle_resize(size);
 	}
 
+	fn handle_auto_policy(&mut self, config: AutoPolicyConfig) {
+		info!("Configuring auto policy");
+
+		self.auto_policy_config = config;
+		self.mini_stack_manager.configure(config);
+	}
+
 	fn handle_policy(
 		&mut self,
 		policy: PaperPolicy,
@@ -247,22 +317,29 @@ where
 
 		*self.current_policy.write() = policy;
 
-		let mini_index = self.mini_stack_manager.get_index(&policy);
+		// the no-eviction policy has no mini stack, but it never evicts
+		// objects while its stack is being reconstructed anyway
+		let mini_index = match policy.is_no_eviction() {
+			true => None,
+			false => self.mini_stack_manager.get_index(&policy),
+		};
 
 		self.policy_stack = None;
-		self.mini_index = Some(mini_index);
+		self.mini_index = mini_index;
 
 		let max_cache_size = self.status.max_size();
+		let custom_policies = self.status.custom_policies().clone();
 		let current_policy = self.current_policy.clone();
 		let trace_fragments = self.trace_fragments.clone();
 
-		thread::spawn(move || {
+		let reconstruction = thread::spawn(move || {
 			info!("Reconstructing {policy} stack");
 			let now = Instant::now();
 
 			let reconstruction_result = reconstruct_policy_stack(
 				policy,
 				max_cache_size,
+				&custom_policies,
 				current_policy.clone(),
 				trace_fragments.clone(),
 			);
@@ -283,6 +360,55 @@ where
 				}
 			}
 		});
+
+		self.track_reconstruction(reconstruction);
+	}
+
+	fn handle_eviction_order(&mut self, sender: Sender<Vec<HashedKey>>) {
+		let policy = *self.current_policy.read();
+		let max_cache_size = self.status.max_size();
+		let custom_policies = self.status.custom_policies().clone();
+		let current_policy = self.current_policy.clone();
+		let trace_fragments = self.trace_fragments.clone();
+
+		// the order is computed on a separately reconstructed stack so that
+		// the current stack is not modified
+		let reconstruction = thread::spawn(move || {
+			let reconstruction_result = reconstruct_policy_stack(
+				policy,
+				max_cache_size,
+				&custom_policies,
+				current_policy,
+				trace_fragments,
+			);
+
+			// if the reconstruction fails, the sender is dropped without
+			// sending any order
+			if let Ok(mut stack) = reconstruction_result {
+				let num_keys = stack.len();
+
+				let order = std::iter::from_fn(|| stack.evict_one())
+					.take(num_keys)
+					.collect();
+
+				sender.send(order).ok();
+			}
+		});
+
+		self.track_reconstruction(reconstruction);
+	}
+
+	/// Returns `true` if the current policy's stack is being reconstructed,
+	/// during which the stack events are buffered.
+	fn is_reconstructing(&self) -> bool {
+		self.policy_stack.is_none()
+	}
+
+	fn track_reconstruction(&mut self, reconstruction: JoinHandle<()>) {
+		self.reconstructions
+			.retain(|reconstruction| !reconstruction.is_finished());
+
+		self.reconstructions.push(reconstruction);
 	}
 
 	fn handle_wipe(&mut self) {
@@ -302,8 +428,9 @@ where
 			for event in buffered_events {
 				match event {
 					StackEvent::Get(key) => stack.update(*key),
-					StackEvent::Set(key, size) => stack.insert(*key, *size),
+					StackEvent::Set(key, size, expiry, hints) => stack.insert_with_hints(*key, *size, *expiry, *hints),
 					StackEvent::Del(key) => stack.remove(*key),
+					StackEvent::Ttl(key, expiry) => stack.update_expiry(*key, *expiry),
 					StackEvent::Wipe => stack.clear(),
 					StackEvent::Resize(size) => stack.resize(*size),
 				}
@@ -320,9 +447,9 @@ where
 		&self,
 		buffered_events: &mut Vec<StackEvent>,
 	) -> Result<(), CacheError> {
-		if self.mini_index.is_some() {
-			// the mini policy is still running so stack events should be buffered
-			// until the full stack is reconstructed
+		if self.is_reconstructing() {
+			// the stack is still being reconstructed so stack events should be
+			// buffered until the full stack is reconstructed
 			return Ok(());
 		}
 
@@ -338,16 +465,26 @@ where
 		Ok(())
 	}
 
-	fn apply_evictions(&mut self, buffered_events: &mut Vec<StackEvent>) -> Result<(), CacheError> {
+	/// Evicts objects until the used size is at most the target size.
+	fn apply_evictions(
+		&mut self,
+		target_size: CacheSize,
+		buffered_events: &mut Vec<StackEvent>,
+	) -> Result<(), CacheError> {
+		if self.current_policy.read().is_no_eviction() {
+			// objects are never evicted, so sets are rejected instead once
+			// the cache is full
+			return Ok(());
+		}
+
 		if let Some(index) = self.mini_index {
-			self.apply_mini_evictions(index, buffered_events);
+			self.apply_mini_evictions(index, target_size, buffered_events);
 			return Ok(());
 		}
 
 		let policy = self.current_policy.read();
-		let max_cache_size = self.status.max_size();
 
-		while self.status.used_size(&policy) > max_cache_size {
+		while self.status.used_size(&policy) > target_size {
 			let Some(policy_stack) = self.policy_stack.as_mut() else {
 				error!("No active policy or mini stack");
 				return Err(CacheError::Internal);
@@ -361,25 +498,32 @@ where
 				&self.objects,
 				&self.status,
 				&self.overhead_manager,
+				&self.removal_listener,
 				maybe_key,
+				RemovalCause::Evicted,
 			);
 
 			let Ok((key, _)) = erase_result else {
 				continue;
 			};
 
+			self.status.set_last_eviction(key);
 			buffered_events.push(StackEvent::Del(key));
 		}
 
 		Ok(())
 	}
 
-	fn apply_mini_evictions(&mut self, mini_index: usize, buffered_events: &mut Vec<StackEvent>) {
-		let max_cache_size = self.status.max_size();
+	fn apply_mini_evictions(
+		&mut self,
+		mini_index: usize,
+		target_size: CacheSize,
+		buffered_events: &mut Vec<StackEvent>,
+	) {
 		let policy = self.current_policy.read();
 		let mut evictions = Vec::<HashedKey>::new();
 
-		while self.status.used_size(&policy) > max_cache_size {
+		while self.status.used_size(&policy) > target_size {
 			let maybe_key = self
 				.mini_stack_manager
 				.get_eviction(mini_index)
@@ -389,13 +533,16 @@ where
 				&self.objects,
 				&self.status,
 				&self.overhead_manager,
+				&self.removal_listener,
 				maybe_key,
+				RemovalCause::Evicted,
 			);
 
 			let Ok((key, _)) = erase_result else {
 				continue;
 			};
 
+			self.status.set_last_eviction(key);
 			evictions.push(key);
 			buffered_events.push(StackEvent::Del(key));
 		}
@@ -404,8 +551,34 @@ where
 			.apply_evictions(mini_index, evictions);
 	}
 
+	fn apply_settles(&mut self) {
+		if self.is_reconstructing() {
+			// a stack is still being reconstructed, so the preceding policy
+			// switch has not yet been fully applied
+			return;
+		}
+
+		for callback in self.pending_settles.drain(..) {
+			callback();
+		}
+	}
+
+	/// Returns the used size at which the overhead of the supplied policy
+	/// fits in the cache.
+	fn auto_policy_target_size(&self, policy: PaperPolicy) -> CacheSize {
+		let current_overhead = self.status.policy_overhead(&self.status.policy());
+		let new_overhead = self.status.policy_overhead(&policy);
+
+		let overhead_delta = new_overhead.saturating_sub(current_overhead) as CacheSize;
+		let num_objects = self.status.num_objects();
+
+		self.status
+			.max_size()
+			.saturating_sub(num_objects * overhead_delta)
+	}
+
 	fn perform_auto_policy(&mut self, now: Instant, has_current_set: bool) -> Option<PaperPolicy> {
-		if has_current_set || !self.status.is_auto_policy() || self.mini_index.is_some() {
+		if has_current_set || !self.status.is_auto_policy() || self.is_reconstructing() {
 			// don't switch the policy while (any of):
 			// * there is recent set activity
 			// * the auto policy is not configured
@@ -413,20 +586,47 @@ where
 			return None;
 		}
 
+		let epoch = self.auto_policy_config.epoch;
+
 		let should_poll_policy = self
 			.last_auto_policy_time
-			.is_none_or(|last_auto_policy_time| now - last_auto_policy_time > AUTO_POLICY_DURATION);
+			.is_none_or(|last_auto_policy_time| now - last_auto_policy_time > epoch);
 
 		if !should_poll_policy {
 			return None;
 		}
 
 		self.last_auto_policy_time = Some(now);
-		self.mini_stack_manager
-			.get_optimal_policy(&self.current_policy.read())
+
+		let mut decision = self
+			.mini_stack_manager
+			.get_optimal_policy(&self.current_policy.read())?;
+
+		let cooldown = self.auto_policy_config.cooldown;
+
+		let is_cooling_down = self
+			.last_auto_policy_switch
+			.is_some_and(|last_auto_policy_switch| now - last_auto_policy_switch < cooldown);
+
+		if decision.is_switch() && is_cooling_down {
+			// the epoch is still evaluated (which clears the mini stacks'
+			// counters) but the policy is not switched
+			decision.reason = AutoPolicyReason::Cooldown;
+		}
+
+		let maybe_policy = decision
+			.is_switch()
+			.then_some(decision.chosen_policy);
+
+		if maybe_policy.is_some() {
+			self.last_auto_policy_switch = Some(now);
+		}
+
+		self.status.record_auto_policy_decision(decision);
+		maybe_policy
 	}
 
-	fn delay_event_loop(&mut self, now: Instant, has_current_set: bool) {
+	fn event_loop_delay(&mut self, now: Instant, has_current_set: bool) -> Duration {
 		let has_recent_set = self
 			.last_set_time
 			.is_some_and(|last_set_time| now - last_set_time <= SET_RECENCY_DURATION);
@@ -435,23 +635,38 @@ where
 			self.last_set_time = Some(now);
 		}
 
-		let delay = if has_recent_set {
+		if has_recent_set {
 			SHORT_POLLING_DURATION
 		} else {
 			LONG_POLLING_DURATION
-		};
+		}
+	}
+
+	fn shutdown(&mut self) -> Result<(), CacheError> {
+		// the reconstructions terminate once the configured policy no longer
+		// matches the policy being reconstructed, and no stack is ever
+		// reconstructed for the auto policy
+		*self.current_policy.write() = PaperPolicy::Auto;
 
-		thread::sleep(delay);
+		for reconstruction in self.reconstructions.drain(..) {
+			reconstruction.join().ok();
+		}
+
+		match self.trace_handle.take() {
+			Some(trace_handle) => trace_handle.shutdown(),
+			None => Ok(()),
+		}
 	}
 }
 
 fn reconstruct_policy_stack(
 	policy: PaperPolicy,
 	max_size: CacheSize,
+	custom_policies: &CustomPolicies,
 	current_policy: Arc<RwLock<PaperPolicy>>,
 	trace_fragments: Arc<RwLock<VecDeque<TraceFragment>>>,
 ) -> Result<Box<dyn PolicyStack>, CacheError> {
-	let mut stack = init_policy_stack(policy, max_size);
+	let mut stack = init_policy_stack(policy, max_size, custom_policies)?;
 
 	for fragment in trace_fragments.read().iter() {
 		let mut fragment_modifiers = fragment.lock();
@@ -472,7 +687,7 @@ fn reconstruct_policy_stack(
 			return Err(CacheError::Internal);
 		}
 
-		for (index, event) in fragment_reader.iter().enumerate() {
+		for (index, event) in TraceEvents::new(fragment_reader.iter()).enumerate() {
 			if index & (RECONSTRUCT_POLICY_POLLING - 1) == 0 && policy != *current_policy.read() {
 				// every RECONSTRUCT_POLICY_POLLING events, check if the currently
 				// configured policy is still the policy we're reconstructing and
@@ -487,8 +702,9 @@ fn reconstruct_policy_stack(
 
 			match event {
 				TraceEvent::Get(key) => stack.update(key),
-				TraceEvent::Set(key, size) => stack.insert(key, size),
+				TraceEvent::Set(key, size, expiry, hints) => stack.insert_with_hints(key, size, expiry, hints),
 				TraceEvent::Del(key) => stack.remove(key),
+				TraceEvent::Ttl(key, expiry) => stack.update_expiry(key, expiry),
 				TraceEvent::Resize(size) => stack.resize(size),
 			}
 		}
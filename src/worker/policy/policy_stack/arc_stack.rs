@@ -16,7 +16,7 @@ use crate::{
 	CacheSize,
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
+	object::{ExpireTime, ObjectSize},
 	policy::PaperPolicy,
 	worker::policy::policy_stack::PolicyStack,
 };
@@ -56,7 +56,7 @@ impl PolicyStack for ArcStack {
 		self.t1.stack.contains(&key) || self.t2.stack.contains(&key)
 	}
 
-	fn insert(&mut self, key: HashedKey, size: ObjectSize) {
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, _: ExpireTime) {
 		if self.t1.stack.contains(&key) || self.t2.stack.contains(&key) {
 			// case 1
 			self.t1.update(key, size);
@@ -278,7 +278,7 @@ mod tests {
 		for access in [
 			0, 1, 0, 2, 1, 3, 0, 4, 2, 5, 0,
 		] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		for eviction in [3, 4, 5, 1, 2, 0] {
@@ -0,0 +1,212 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	cmp::Ordering,
+	collections::{BTreeSet, HashMap},
+};
+
+use crate::{
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectCost, ObjectHints, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+#[derive(Default)]
+pub struct GdsfStack {
+	objects:    HashMap<HashedKey, Object, NoHasher>,
+	priorities: BTreeSet<(Priority, HashedKey)>,
+
+	// the priority of the most recently evicted object, which is added to
+	// the priority of every accessed object so that objects which are no
+	// longer accessed are eventually evicted
+	inflation: f64,
+	clock:     u64,
+}
+
+struct Object {
+	size:      ObjectSize,
+	cost:      ObjectCost,
+	class:     u8,
+	frequency: u32,
+	priority:  Priority,
+}
+
+// objects in lower priority classes are evicted first, and objects with the
+// same priority are evicted in the order they were accessed
+#[derive(Clone, Copy, Default)]
+struct Priority {
+	class: u8,
+	value: f64,
+	time:  u64,
+}
+
+impl PolicyStack for GdsfStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		matches!(policy, PaperPolicy::Gdsf)
+	}
+
+	fn len(&self) -> usize {
+		self.objects.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.objects.contains_key(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, expiry: ExpireTime) {
+		self.insert_with_hints(key, size, expiry, ObjectHints::default());
+	}
+
+	fn insert_with_hints(&mut self, key: HashedKey, size: ObjectSize, _: ExpireTime, hints: ObjectHints) {
+		if let Some(object) = self.objects.get_mut(&key) {
+			object.size = size;
+			object.cost = hints.cost;
+			object.class = hints.priority;
+
+			return self.update(key);
+		}
+
+		let object = Object {
+			size,
+			cost: hints.cost,
+			class: hints.priority,
+			frequency: 0,
+			priority: Priority::default(),
+		};
+
+		self.objects.insert(key, object);
+		self.access(key);
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		if self.objects.contains_key(&key) {
+			self.access(key);
+		}
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		if let Some(object) = self.objects.remove(&key) {
+			self.priorities.remove(&(object.priority, key));
+		}
+	}
+
+	fn clear(&mut self) {
+		self.objects.clear();
+		self.priorities.clear();
+
+		self.inflation = 0.0;
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		let (priority, key) = self.priorities.pop_first()?;
+
+		self.objects.remove(&key);
+		self.inflation = priority.value;
+
+		Some(key)
+	}
+}
+
+impl GdsfStack {
+	fn access(&mut self, key: HashedKey) {
+		let Some(object) = self.objects.get_mut(&key) else {
+			return;
+		};
+
+		self.priorities.remove(&(object.priority, key));
+
+		self.clock += 1;
+		object.frequency = object.frequency.saturating_add(1);
+
+		// zero-sized objects are treated as one byte so that their
+		// priority remains finite
+		let size = object.size.max(1) as f64;
+
+		object.priority = Priority {
+			class: object.class,
+			value: self.inflation + object.frequency as f64 * object.cost as f64 / size,
+			time:  self.clock,
+		};
+
+		self.priorities.insert((object.priority, key));
+	}
+}
+
+impl Ord for Priority {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.class
+			.cmp(&other.class)
+			.then(self.value.total_cmp(&other.value))
+			.then(self.time.cmp(&other.time))
+	}
+}
+
+impl PartialOrd for Priority {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl PartialEq for Priority {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Priority {}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{GdsfStack, PolicyStack};
+
+		let mut stack = GdsfStack::default();
+
+		for (access, size) in [(0, 1), (1, 4), (2, 2), (1, 4)] {
+			stack.insert(access, size, None);
+		}
+
+		// 1 was accessed twice but is twice the size of 2, so their
+		// priorities are equal and 2 is evicted as it was accessed first
+		assert_eq!(stack.evict_one(), Some(2));
+
+		// the priority of 3 is inflated by the priority of 2
+		stack.insert(3, 1, None);
+
+		for eviction in [1, 0, 3] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+
+	#[test]
+	fn eviction_order_with_hints_is_correct() {
+		use crate::{
+			object::ObjectHints,
+			worker::policy::policy_stack::{GdsfStack, PolicyStack},
+		};
+
+		let mut stack = GdsfStack::default();
+
+		for (access, cost, priority) in [(0, 1, 1), (1, 10, 0), (2, 5, 0), (3, 20, 1)] {
+			stack.insert_with_hints(access, 1, None, ObjectHints { cost, priority });
+		}
+
+		// objects in the lower priority class are evicted first regardless of
+		// their costs, and then in order of their costs
+		for eviction in [2, 1, 0, 3] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+}
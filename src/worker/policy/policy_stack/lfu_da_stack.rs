@@ -0,0 +1,119 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+#[derive(Default)]
+pub struct LfuDaStack {
+	objects:    HashMap<HashedKey, Object, NoHasher>,
+	priorities: BTreeSet<(Priority, HashedKey)>,
+
+	// the priority of the most recently evicted object, which is added to
+	// the count of every accessed object so that objects which were popular
+	// in the past but are no longer accessed are eventually evicted
+	age:   u64,
+	clock: u64,
+}
+
+#[derive(Default)]
+struct Object {
+	count:    u32,
+	priority: Priority,
+}
+
+// objects with the same priority are evicted in the order they were accessed
+type Priority = (u64, u64);
+
+impl PolicyStack for LfuDaStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		matches!(policy, PaperPolicy::LfuDa)
+	}
+
+	fn len(&self) -> usize {
+		self.objects.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.objects.contains_key(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
+		self.objects.entry(key).or_default();
+		self.update(key);
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		let Some(object) = self.objects.get_mut(&key) else {
+			return;
+		};
+
+		self.priorities.remove(&(object.priority, key));
+
+		self.clock += 1;
+		object.count = object.count.saturating_add(1);
+		object.priority = (self.age + object.count as u64, self.clock);
+
+		self.priorities.insert((object.priority, key));
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		if let Some(object) = self.objects.remove(&key) {
+			self.priorities.remove(&(object.priority, key));
+		}
+	}
+
+	fn clear(&mut self) {
+		self.objects.clear();
+		self.priorities.clear();
+
+		self.age = 0;
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		let ((priority, _), key) = self.priorities.pop_first()?;
+
+		self.objects.remove(&key);
+		self.age = priority;
+
+		Some(key)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{LfuDaStack, PolicyStack};
+
+		let mut stack = LfuDaStack::default();
+
+		for access in [0, 0, 0, 1, 2] {
+			stack.insert(access, 1, None);
+		}
+
+		assert_eq!(stack.evict_one(), Some(1));
+
+		// 3 is accessed fewer times than 0, but its priority is aged
+		// by the priority of 1 so it is evicted after 0
+		stack.insert(3, 1, None);
+		stack.insert(3, 1, None);
+
+		for eviction in [2, 0, 3] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+}
@@ -13,7 +13,7 @@ use kwik::collections::HashList;
 use crate::{
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
+	object::{ExpireTime, ObjectSize},
 	policy::PaperPolicy,
 	worker::policy::policy_stack::PolicyStack,
 };
@@ -42,7 +42,7 @@ impl PolicyStack for LfuStack {
 		self.index_map.contains_key(&key)
 	}
 
-	fn insert(&mut self, key: HashedKey, _: ObjectSize) {
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
 		if self.index_map.contains_key(&key) {
 			return self.update(key);
 		}
@@ -187,7 +187,7 @@ mod tests {
 		for access in [
 			0, 1, 1, 1, 0, 2, 3, 0, 2, 0,
 		] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		for eviction in [3, 2, 1, 0] {
@@ -0,0 +1,395 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	borrow::Borrow,
+	hash::{Hash, Hasher},
+};
+
+use kwik::collections::HashList;
+
+use crate::{
+	CacheSize,
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+pub struct LirsStack {
+	hir_ratio: f64,
+	max_size:  CacheSize,
+
+	// the recency stack of LIR, resident HIR, and non-resident HIR objects,
+	// which is pruned so that its bottom object is always LIR
+	stack: HashList<Object, NoHasher>,
+
+	// the resident HIR objects in the order they are evicted
+	queue: HashList<Object, NoHasher>,
+
+	// the non-resident HIR objects in the recency stack from the most to
+	// the least recently evicted
+	ghosts: HashList<HashedKey, NoHasher>,
+
+	num_lir:         usize,
+	lir_used_size:   CacheSize,
+	ghost_used_size: CacheSize,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Status {
+	Lir,
+	ResidentHir,
+	NonResidentHir,
+}
+
+struct Object {
+	key:    HashedKey,
+	size:   ObjectSize,
+	status: Status,
+}
+
+impl PolicyStack for LirsStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		let PaperPolicy::Lirs(hir_ratio) = policy else {
+			return false;
+		};
+
+		self.hir_ratio == *hir_ratio
+	}
+
+	fn len(&self) -> usize {
+		self.num_lir + self.queue.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.queue.contains(&key) || self.status(key) == Some(Status::Lir)
+	}
+
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, _: ExpireTime) {
+		if self.contains(key) {
+			self.update_size(key, size);
+			self.update(key);
+
+			// the object may have grown beyond the LIR objects' share
+			return self.demote_lir();
+		}
+
+		if self.status(key) == Some(Status::NonResidentHir) {
+			// the object was re-accessed within the recency of the LIR
+			// objects, so its inter-reference recency makes it LIR
+			if let Some(object) = self.stack.remove(&key) {
+				self.remove_ghost(key, object.size);
+			}
+
+			return self.insert_lir(key, size);
+		}
+
+		if self.lir_used_size + size as CacheSize <= self.lir_max_size() {
+			// the LIR objects have not yet filled their share of the cache
+			return self.insert_lir(key, size);
+		}
+
+		self.stack
+			.push_front(Object::new(key, size, Status::ResidentHir));
+		self.queue
+			.push_front(Object::new(key, size, Status::ResidentHir));
+
+		// if there are no LIR objects (e.g., every object is larger than their
+		// share of the cache), the HIR object is at the bottom of the stack
+		self.prune();
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		match self.status(key) {
+			Some(Status::Lir) => {
+				self.stack.move_front(&key);
+				self.prune();
+			},
+
+			Some(Status::ResidentHir) => {
+				let Some(object) = self.queue.remove(&key) else {
+					return;
+				};
+
+				self.stack.remove(&key);
+				self.insert_lir(key, object.size);
+			},
+
+			_ => {
+				// the resident HIR object is no longer in the recency stack,
+				// so it stays HIR but becomes the most recently used
+				let Some(object) = self.queue.get(&key) else {
+					return;
+				};
+
+				let object = Object::new(key, object.size, Status::ResidentHir);
+
+				self.stack.push_front(object);
+				self.queue.move_front(&key);
+				self.prune();
+			},
+		}
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		if let Some(object) = self.stack.remove(&key) {
+			match object.status {
+				Status::Lir => {
+					self.num_lir -= 1;
+					self.lir_used_size -= object.size as CacheSize;
+				},
+
+				Status::NonResidentHir => self.remove_ghost(key, object.size),
+				Status::ResidentHir => {},
+			}
+		}
+
+		self.queue.remove(&key);
+		self.prune();
+	}
+
+	fn resize(&mut self, max_size: CacheSize) {
+		self.max_size = max_size;
+
+		self.demote_lir();
+		self.trim_ghosts();
+	}
+
+	fn clear(&mut self) {
+		self.stack.clear();
+		self.queue.clear();
+		self.ghosts.clear();
+
+		self.num_lir = 0;
+		self.lir_used_size = 0;
+		self.ghost_used_size = 0;
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		if let Some(object) = self.queue.pop_back() {
+			if self.stack.contains(&object.key) {
+				// keep the evicted object's history so that it can become LIR
+				// if it is accessed again soon
+				self.stack
+					.update(&object.key, |object| object.status = Status::NonResidentHir);
+
+				self.ghosts.push_front(object.key);
+				self.ghost_used_size += object.size as CacheSize;
+				self.trim_ghosts();
+			}
+
+			return Some(object.key);
+		}
+
+		let object = self.stack.pop_back()?;
+
+		self.num_lir -= 1;
+		self.lir_used_size -= object.size as CacheSize;
+		self.prune();
+
+		Some(object.key)
+	}
+}
+
+impl LirsStack {
+	pub fn new(hir_ratio: f64, max_size: CacheSize) -> Self {
+		LirsStack {
+			hir_ratio,
+			max_size,
+
+			stack: HashList::with_hasher(NoHasher::default()),
+			queue: HashList::with_hasher(NoHasher::default()),
+			ghosts: HashList::with_hasher(NoHasher::default()),
+
+			num_lir: 0,
+			lir_used_size: 0,
+			ghost_used_size: 0,
+		}
+	}
+
+	fn lir_max_size(&self) -> CacheSize {
+		((1.0 - self.hir_ratio) * self.max_size as f64) as u64
+	}
+
+	fn status(&self, key: HashedKey) -> Option<Status> {
+		self.stack.get(&key).map(|object| object.status)
+	}
+
+	fn update_size(&mut self, key: HashedKey, size: ObjectSize) {
+		if let Some(object) = self.stack.get(&key)
+			&& object.status == Status::Lir
+		{
+			self.lir_used_size -= object.size as CacheSize;
+			self.lir_used_size += size as CacheSize;
+		}
+
+		self.stack.update(&key, |object| object.size = size);
+		self.queue.update(&key, |object| object.size = size);
+	}
+
+	fn insert_lir(&mut self, key: HashedKey, size: ObjectSize) {
+		self.num_lir += 1;
+		self.lir_used_size += size as CacheSize;
+		self.stack
+			.push_front(Object::new(key, size, Status::Lir));
+
+		self.demote_lir();
+	}
+
+	/// Moves the LIR objects at the bottom of the recency stack to the HIR
+	/// queue until the LIR objects fit in their share of the cache.
+	fn demote_lir(&mut self) {
+		while self.lir_used_size > self.lir_max_size() {
+			// the bottom of the stack must be LIR for it to be demoted
+			self.prune();
+
+			let Some(object) = self.stack.pop_back() else {
+				return;
+			};
+
+			self.num_lir -= 1;
+			self.lir_used_size -= object.size as CacheSize;
+			self.queue
+				.push_front(Object::new(object.key, object.size, Status::ResidentHir));
+		}
+
+		self.prune();
+	}
+
+	/// Removes the HIR objects from the bottom of the recency stack so that
+	/// the least recently used object in the stack is LIR.
+	fn prune(&mut self) {
+		while let Some(object) = self.stack.back()
+			&& object.status != Status::Lir
+		{
+			let Some(object) = self.stack.pop_back() else {
+				return;
+			};
+
+			if object.status == Status::NonResidentHir {
+				self.remove_ghost(object.key, object.size);
+			}
+		}
+	}
+
+	/// Limits the total size of the non-resident HIR objects to the size of
+	/// the cache, forgetting the least recently evicted objects first.
+	fn trim_ghosts(&mut self) {
+		while self.ghost_used_size > self.max_size {
+			let Some(key) = self.ghosts.back().copied() else {
+				return;
+			};
+
+			let Some(object) = self.stack.remove(&key) else {
+				self.ghosts.remove(&key);
+				continue;
+			};
+
+			self.remove_ghost(key, object.size);
+		}
+	}
+
+	fn remove_ghost(&mut self, key: HashedKey, size: ObjectSize) {
+		if self.ghosts.remove(&key).is_some() {
+			self.ghost_used_size -= size as CacheSize;
+		}
+	}
+}
+
+impl Object {
+	fn new(key: HashedKey, size: ObjectSize, status: Status) -> Self {
+		Object {
+			key,
+			size,
+			status,
+		}
+	}
+}
+
+impl Borrow<HashedKey> for Object {
+	fn borrow(&self) -> &HashedKey {
+		&self.key
+	}
+}
+
+impl Hash for Object {
+	fn hash<H>(&self, state: &mut H)
+	where
+		H: Hasher,
+	{
+		self.key.hash(state)
+	}
+}
+
+impl PartialEq for Object {
+	fn eq(&self, other: &Self) -> bool {
+		self.key == other.key
+	}
+}
+
+impl Eq for Object {}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{LirsStack, PolicyStack};
+
+		let mut stack = LirsStack::new(0.5, 4);
+
+		for access in [0, 1, 2, 3, 2, 4] {
+			stack.insert(access, 1, None);
+		}
+
+		assert_eq!(stack.evict_one(), Some(3));
+
+		// 3 is accessed again while it is still in the recency stack,
+		// so it becomes LIR
+		stack.insert(3, 1, None);
+
+		for eviction in [0, 4, 1, 2, 3] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+
+	#[test]
+	fn it_tracks_objects_larger_than_the_lir_share() {
+		use crate::worker::policy::policy_stack::{LirsStack, PolicyStack};
+
+		let mut stack = LirsStack::new(0.5, 4);
+
+		// the first two objects are larger than the LIR objects' share of the
+		// cache, so they are HIR while there are no LIR objects
+		stack.insert(100, 3, None);
+		stack.insert(104, 3, None);
+		stack.insert(101, 1, None);
+
+		assert_eq!(stack.len(), 3);
+
+		stack.update(104);
+		assert_eq!(stack.len(), 3);
+
+		for key in [100, 101, 104] {
+			assert!(stack.contains(key));
+		}
+
+		stack.remove(104);
+		assert_eq!(stack.len(), 2);
+		assert!(!stack.contains(104));
+
+		let mut evictions = [stack.evict_one(), stack.evict_one()];
+		evictions.sort();
+
+		assert_eq!(evictions, [Some(100), Some(101)]);
+		assert_eq!(stack.evict_one(), None);
+		assert_eq!(stack.len(), 0);
+	}
+}
@@ -0,0 +1,192 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use kwik::collections::HashList;
+
+use crate::{
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+pub struct LruKStack {
+	k:     u32,
+	clock: u64,
+
+	histories:  HashMap<HashedKey, History, NoHasher>,
+	priorities: BTreeSet<(Priority, HashedKey)>,
+
+	// the histories of recently evicted objects, ordered from the most to
+	// the least recently evicted
+	ghosts:          HashList<HashedKey, NoHasher>,
+	ghost_histories: HashMap<HashedKey, History, NoHasher>,
+}
+
+// objects with fewer than K accesses are evicted first (ordered by their
+// most recent access), followed by the objects whose K-th most recent
+// access is the oldest
+type Priority = (bool, u64);
+
+#[derive(Default)]
+struct History {
+	// the access times from the most to the least recent
+	accesses: VecDeque<u64>,
+}
+
+impl PolicyStack for LruKStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		let PaperPolicy::LruK(k) = policy else {
+			return false;
+		};
+
+		self.k == *k
+	}
+
+	fn len(&self) -> usize {
+		self.histories.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.histories.contains_key(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
+		if self.histories.contains_key(&key) {
+			return self.update(key);
+		}
+
+		let history = match self.ghost_histories.remove(&key) {
+			Some(history) => {
+				self.ghosts.remove(&key);
+				history
+			},
+
+			None => History::default(),
+		};
+
+		self.histories.insert(key, history);
+		self.access(key);
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		if self.histories.contains_key(&key) {
+			self.access(key);
+		}
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		if let Some(history) = self.histories.remove(&key) {
+			self.priorities.remove(&(history.priority(self.k), key));
+		}
+
+		self.ghosts.remove(&key);
+		self.ghost_histories.remove(&key);
+	}
+
+	fn clear(&mut self) {
+		self.histories.clear();
+		self.priorities.clear();
+
+		self.ghosts.clear();
+		self.ghost_histories.clear();
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		let (_, key) = self.priorities.pop_first()?;
+		let history = self.histories.remove(&key)?;
+
+		self.ghosts.push_front(key);
+		self.ghost_histories.insert(key, history);
+
+		// the history is only retained for as many evicted objects as there
+		// are objects in the cache
+		while self.ghosts.len() > self.histories.len() {
+			let Some(ghost_key) = self.ghosts.pop_back() else {
+				break;
+			};
+
+			self.ghost_histories.remove(&ghost_key);
+		}
+
+		Some(key)
+	}
+}
+
+impl LruKStack {
+	pub fn new(k: u32) -> Self {
+		LruKStack {
+			k,
+			clock: 0,
+
+			histories: HashMap::with_hasher(NoHasher::default()),
+			priorities: BTreeSet::new(),
+
+			ghosts: HashList::with_hasher(NoHasher::default()),
+			ghost_histories: HashMap::with_hasher(NoHasher::default()),
+		}
+	}
+
+	fn access(&mut self, key: HashedKey) {
+		let Some(history) = self.histories.get_mut(&key) else {
+			return;
+		};
+
+		self.priorities.remove(&(history.priority(self.k), key));
+
+		self.clock += 1;
+		history.record(self.clock, self.k);
+
+		self.priorities.insert((history.priority(self.k), key));
+	}
+}
+
+impl History {
+	fn record(&mut self, time: u64, k: u32) {
+		self.accesses.push_front(time);
+		self.accesses.truncate(k as usize);
+	}
+
+	fn priority(&self, k: u32) -> Priority {
+		let has_k_accesses = self.accesses.len() >= k as usize;
+
+		let time = match has_k_accesses {
+			true => self.accesses.back(),
+			false => self.accesses.front(),
+		};
+
+		(has_k_accesses, time.copied().unwrap_or_default())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{LruKStack, PolicyStack};
+
+		let mut stack = LruKStack::new(2);
+
+		for access in [0, 1, 0, 2, 1, 3] {
+			stack.insert(access, 1, None);
+		}
+
+		assert_eq!(stack.evict_one(), Some(2));
+
+		// the history of 2 was retained, so this is its second access
+		stack.insert(2, 1, None);
+
+		for eviction in [3, 0, 1, 2] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+}
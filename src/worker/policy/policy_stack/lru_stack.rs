@@ -10,7 +10,7 @@ use kwik::collections::HashList;
 use crate::{
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
+	object::{ExpireTime, ObjectSize},
 	policy::PaperPolicy,
 	worker::policy::policy_stack::PolicyStack,
 };
@@ -33,7 +33,7 @@ impl PolicyStack for LruStack {
 		self.stack.contains(&key)
 	}
 
-	fn insert(&mut self, key: HashedKey, _: ObjectSize) {
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
 		if self.stack.contains(&key) {
 			return self.update(key);
 		}
@@ -69,7 +69,7 @@ mod tests {
 		for access in [
 			0, 1, 1, 1, 0, 2, 3, 0, 2, 0,
 		] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		for eviction in [1, 3, 2, 0] {
@@ -8,51 +8,119 @@
 mod arc_stack;
 mod clock_stack;
 mod fifo_stack;
+mod gdsf_stack;
+mod lfu_da_stack;
 mod lfu_stack;
+mod lirs_stack;
+mod lru_k_stack;
 mod lru_stack;
 mod mru_stack;
+mod multi_clock_stack;
+mod no_eviction_stack;
 mod s_three_fifo_stack;
+mod sampled_stack;
 mod sieve_stack;
+mod slru_stack;
 mod two_q_stack;
+mod volatile_ttl_stack;
+mod w_tinylfu_stack;
 
 use crate::{
 	CacheSize,
 	HashedKey,
-	object::ObjectSize,
-	policy::PaperPolicy,
+	error::CacheError,
+	object::{ExpireTime, ObjectHints, ObjectSize},
+	policy::{CustomPolicies, PaperPolicy},
 	worker::policy::policy_stack::{
 		arc_stack::ArcStack,
 		clock_stack::ClockStack,
 		fifo_stack::FifoStack,
+		gdsf_stack::GdsfStack,
+		lfu_da_stack::LfuDaStack,
 		lfu_stack::LfuStack,
+		lirs_stack::LirsStack,
+		lru_k_stack::LruKStack,
 		lru_stack::LruStack,
 		mru_stack::MruStack,
+		multi_clock_stack::MultiClockStack,
+		no_eviction_stack::NoEvictionStack,
 		s_three_fifo_stack::SThreeFifoStack,
+		sampled_stack::{SampledStack, Sampling},
 		sieve_stack::SieveStack,
+		slru_stack::SlruStack,
 		two_q_stack::TwoQStack,
+		volatile_ttl_stack::VolatileTtlStack,
+		w_tinylfu_stack::WTinyLfuStack,
 	},
 };
 
+/// An eviction policy, which orders the cache's hashed keys by when they
+/// should be evicted. Custom policies implement this trait and are registered
+/// with [`PaperCacheBuilder::custom_policy`](crate::PaperCacheBuilder::custom_policy).
+///
+/// A stack is driven by the cache's operations on a worker thread, and is
+/// also reconstructed from the cache's access trace when the cache switches
+/// to its policy.
 pub trait PolicyStack
 where
 	Self: Send,
 {
+	/// Returns `true` if the stack implements the supplied policy.
 	fn is_policy(&self, policy: &PaperPolicy) -> bool;
+
+	/// Returns the number of keys in the stack.
 	fn len(&self) -> usize;
 
+	/// Returns `true` if the stack has no keys.
+	fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns `true` if the stack contains the supplied key.
 	fn contains(&self, key: HashedKey) -> bool;
-	fn insert(&mut self, key: HashedKey, size: ObjectSize);
+
+	/// Inserts the supplied key with the object's size and expiry. If the key
+	/// is already in the stack, the insertion counts as an access.
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, expiry: ExpireTime);
+
+	/// Inserts the supplied key like [`PolicyStack::insert`], along with the
+	/// object's miss cost and priority class. Cost-aware policies override
+	/// this method, while the others ignore the hints.
+	fn insert_with_hints(
+		&mut self,
+		key: HashedKey,
+		size: ObjectSize,
+		expiry: ExpireTime,
+		_hints: ObjectHints,
+	) {
+		self.insert(key, size, expiry);
+	}
+
+	/// Records an access of the supplied key.
 	fn update(&mut self, _key: HashedKey) {}
+
+	/// Updates the expiry of the supplied key.
+	fn update_expiry(&mut self, _key: HashedKey, _expiry: ExpireTime) {}
+
+	/// Removes the supplied key from the stack.
 	fn remove(&mut self, key: HashedKey);
 
+	/// Sets the maximum size of the cache.
 	fn resize(&mut self, _size: CacheSize) {}
+
+	/// Removes every key from the stack.
 	fn clear(&mut self);
 
+	/// Removes and returns the next key to be evicted.
 	fn evict_one(&mut self) -> Option<HashedKey>;
 }
 
-pub fn init_policy_stack(policy: PaperPolicy, max_size: CacheSize) -> Box<dyn PolicyStack> {
-	match policy {
+pub fn init_policy_stack(
+	policy: PaperPolicy,
+	max_size: CacheSize,
+	custom_policies: &CustomPolicies,
+) -> Result<Box<dyn PolicyStack>, CacheError> {
+	let stack: Box<dyn PolicyStack> = match policy {
 		PaperPolicy::Auto => Box::new(LfuStack::default()),
 		PaperPolicy::Lfu => Box::new(LfuStack::default()),
 		PaperPolicy::Fifo => Box::new(FifoStack::default()),
@@ -63,5 +131,23 @@ pub fn init_policy_stack(policy: PaperPolicy, max_size: CacheSize) -> Box<dyn Po
 		PaperPolicy::TwoQ(k_in, k_out) => Box::new(TwoQStack::new(k_in, k_out, max_size)),
 		PaperPolicy::Arc => Box::new(ArcStack::new(max_size)),
 		PaperPolicy::SThreeFifo(ratio) => Box::new(SThreeFifoStack::new(ratio, max_size)),
-	}
+		PaperPolicy::WTinyLfu(ratio) => Box::new(WTinyLfuStack::new(ratio, max_size)),
+		PaperPolicy::Lirs(hir_ratio) => Box::new(LirsStack::new(hir_ratio, max_size)),
+		PaperPolicy::LruK(k) => Box::new(LruKStack::new(k)),
+		PaperPolicy::Gdsf => Box::new(GdsfStack::default()),
+		PaperPolicy::SampledLru(samples) => Box::new(SampledStack::new(Sampling::Lru, samples)),
+		PaperPolicy::SampledLfu(samples) => Box::new(SampledStack::new(Sampling::Lfu, samples)),
+		PaperPolicy::Random => Box::new(SampledStack::new(Sampling::Random, 1)),
+		PaperPolicy::LfuDa => Box::new(LfuDaStack::default()),
+		PaperPolicy::Slru(ratio) => Box::new(SlruStack::new(ratio, max_size)),
+		PaperPolicy::MultiClock(bits) => Box::new(MultiClockStack::new(bits)),
+		PaperPolicy::VolatileTtl => Box::new(VolatileTtlStack::default()),
+		PaperPolicy::NoEviction => Box::new(NoEvictionStack::default()),
+
+		PaperPolicy::Custom(name) => custom_policies
+			.init_stack(name, max_size)
+			.ok_or(CacheError::UnregisteredPolicy)?,
+	};
+
+	Ok(stack)
 }
@@ -10,7 +10,7 @@ use kwik::collections::HashList;
 use crate::{
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
+	object::{ExpireTime, ObjectSize},
 	policy::PaperPolicy,
 	worker::policy::policy_stack::PolicyStack,
 };
@@ -49,7 +49,7 @@ impl PolicyStack for MruStack {
 		self.stack.contains(&key)
 	}
 
-	fn insert(&mut self, key: HashedKey, _: ObjectSize) {
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
 		if self.stack.contains(&key) {
 			return self.update(key);
 		}
@@ -115,17 +115,17 @@ mod tests {
 		let mut stack = MruStack::default();
 
 		for access in [0, 1, 0, 2] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		// it should skip the immediately most recently accessed key
 		assert_eq!(stack.evict_one(), Some(0));
 
-		stack.insert(3, 1);
+		stack.insert(3, 1, None);
 		assert_eq!(stack.evict_one(), Some(2));
 
 		for access in [3, 0, 2, 0] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		for eviction in [2, 3, 1, 0] {
@@ -0,0 +1,161 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	borrow::Borrow,
+	hash::{Hash, Hasher},
+};
+
+use kwik::collections::HashList;
+
+use crate::{
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+pub struct MultiClockStack {
+	bits:      u8,
+	max_count: u8,
+
+	stack: HashList<Object, NoHasher>,
+}
+
+struct Object {
+	key:   HashedKey,
+	count: u8,
+}
+
+impl PolicyStack for MultiClockStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		let PaperPolicy::MultiClock(bits) = policy else {
+			return false;
+		};
+
+		self.bits == *bits
+	}
+
+	fn len(&self) -> usize {
+		self.stack.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.stack.contains(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
+		if self.stack.contains(&key) {
+			return self.update(key);
+		}
+
+		self.stack.push_front(Object::new(key));
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		let max_count = self.max_count;
+
+		self.stack.update(&key, |object| {
+			object.count = object.count.saturating_add(1).min(max_count);
+		});
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		self.stack.remove(&key);
+	}
+
+	fn clear(&mut self) {
+		self.stack.clear();
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		loop {
+			let mut object = self.stack.pop_back()?;
+
+			if object.count == 0 {
+				return Some(object.key);
+			}
+
+			object.count -= 1;
+			self.stack.push_front(object);
+		}
+	}
+}
+
+impl MultiClockStack {
+	pub fn new(bits: u8) -> Self {
+		let max_count = match bits {
+			8.. => u8::MAX,
+			_ => (1 << bits) - 1,
+		};
+
+		MultiClockStack {
+			bits,
+			max_count,
+
+			stack: HashList::with_hasher(NoHasher::default()),
+		}
+	}
+}
+
+impl Object {
+	fn new(key: HashedKey) -> Self {
+		Object {
+			key,
+			count: 0,
+		}
+	}
+}
+
+impl Borrow<HashedKey> for Object {
+	fn borrow(&self) -> &HashedKey {
+		&self.key
+	}
+}
+
+impl Hash for Object {
+	fn hash<H>(&self, state: &mut H)
+	where
+		H: Hasher,
+	{
+		self.key.hash(state)
+	}
+}
+
+impl PartialEq for Object {
+	fn eq(&self, other: &Self) -> bool {
+		self.key == other.key
+	}
+}
+
+impl Eq for Object {}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{MultiClockStack, PolicyStack};
+
+		let mut stack = MultiClockStack::new(2);
+
+		for access in [0, 0, 0, 0, 1, 1, 2] {
+			stack.insert(access, 1, None);
+		}
+
+		assert_eq!(stack.evict_one(), Some(2));
+
+		stack.insert(3, 1, None);
+
+		// the count of 0 saturated at 3, so it survives two passes
+		for eviction in [1, 3, 0] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+}
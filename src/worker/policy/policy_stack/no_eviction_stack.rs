@@ -0,0 +1,68 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashSet;
+
+use crate::{
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+#[derive(Default)]
+pub struct NoEvictionStack {
+	keys: HashSet<HashedKey, NoHasher>,
+}
+
+impl PolicyStack for NoEvictionStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		matches!(policy, PaperPolicy::NoEviction)
+	}
+
+	fn len(&self) -> usize {
+		self.keys.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.keys.contains(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
+		self.keys.insert(key);
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		self.keys.remove(&key);
+	}
+
+	fn clear(&mut self) {
+		self.keys.clear();
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn it_never_evicts() {
+		use crate::worker::policy::policy_stack::{NoEvictionStack, PolicyStack};
+
+		let mut stack = NoEvictionStack::default();
+
+		for access in [0, 1, 0, 2] {
+			stack.insert(access, 1, None);
+		}
+
+		assert_eq!(stack.len(), 3);
+		assert_eq!(stack.evict_one(), None);
+	}
+}
@@ -17,7 +17,7 @@ use crate::{
 	CacheSize,
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
+	object::{ExpireTime, ObjectSize},
 	policy::PaperPolicy,
 	worker::policy::policy_stack::PolicyStack,
 };
@@ -60,7 +60,7 @@ impl PolicyStack for SThreeFifoStack {
 		self.any_stack_contains(key)
 	}
 
-	fn insert(&mut self, key: HashedKey, size: ObjectSize) {
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, _: ExpireTime) {
 		if self.any_stack_contains(key) {
 			self.small.update(key, size);
 			self.main.update(key, size);
@@ -273,7 +273,7 @@ mod tests {
 		for access in [
 			0, 1, 0, 2, 1, 3, 0, 4, 2, 5, 0,
 		] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		for eviction in [1, 2, 3, 4, 5, 0] {
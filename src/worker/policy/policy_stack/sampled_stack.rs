@@ -0,0 +1,321 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+
+use crate::{
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+const RNG_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+/// Approximates an eviction policy by evicting the best of a number of
+/// randomly sampled objects rather than maintaining an ordering of every
+/// object, so only a few bytes of metadata are stored per object.
+pub struct SampledStack {
+	sampling: Sampling,
+	samples:  u32,
+
+	// the objects are stored contiguously so that they can be sampled
+	// uniformly, and the index of each object is kept for its removal
+	indices: HashMap<HashedKey, usize, NoHasher>,
+	objects: Vec<Object>,
+
+	clock: u32,
+	rng:   u64,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Sampling {
+	Lru,
+	Lfu,
+	Random,
+}
+
+struct Object {
+	key: HashedKey,
+
+	// the access time for LRU sampling, or the access count for LFU sampling
+	metadata: u32,
+}
+
+impl PolicyStack for SampledStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		match policy {
+			PaperPolicy::SampledLru(samples) => self.sampling == Sampling::Lru && self.samples == *samples,
+			PaperPolicy::SampledLfu(samples) => self.sampling == Sampling::Lfu && self.samples == *samples,
+			PaperPolicy::Random => self.sampling == Sampling::Random,
+
+			_ => false,
+		}
+	}
+
+	fn len(&self) -> usize {
+		self.objects.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.indices.contains_key(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
+		if self.indices.contains_key(&key) {
+			return self.update(key);
+		}
+
+		self.clock = self.clock.wrapping_add(1);
+
+		let metadata = match self.sampling {
+			Sampling::Lru => self.clock,
+			Sampling::Lfu => 1,
+			Sampling::Random => 0,
+		};
+
+		self.indices.insert(key, self.objects.len());
+		self.objects.push(Object {
+			key,
+			metadata,
+		});
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		let Some(index) = self.indices.get(&key) else {
+			return;
+		};
+
+		self.clock = self.clock.wrapping_add(1);
+
+		let object = &mut self.objects[*index];
+
+		match self.sampling {
+			Sampling::Lru => object.metadata = self.clock,
+			Sampling::Lfu => object.metadata = object.metadata.saturating_add(1),
+			Sampling::Random => {},
+		}
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		let Some(index) = self.indices.remove(&key) else {
+			return;
+		};
+
+		self.objects.swap_remove(index);
+
+		if let Some(object) = self.objects.get(index) {
+			self.indices.insert(object.key, index);
+		}
+	}
+
+	fn clear(&mut self) {
+		self.indices.clear();
+		self.objects.clear();
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		let index = self.sample()?;
+		let key = self.objects[index].key;
+
+		self.remove(key);
+
+		Some(key)
+	}
+}
+
+impl SampledStack {
+	pub fn new(sampling: Sampling, samples: u32) -> Self {
+		SampledStack {
+			sampling,
+			samples: samples.max(1),
+
+			indices: HashMap::with_hasher(NoHasher::default()),
+			objects: Vec::new(),
+
+			clock: 0,
+			rng:   RNG_SEED,
+		}
+	}
+
+	/// Returns the index of the best eviction candidate among the sampled
+	/// objects. If there are no more objects than samples, every object is
+	/// considered.
+	fn sample(&mut self) -> Option<usize> {
+		if self.objects.is_empty() {
+			return None;
+		}
+
+		if self.sampling == Sampling::Random {
+			return Some(self.next_index());
+		}
+
+		if self.objects.len() <= self.samples as usize {
+			return (0..self.objects.len()).max_by_key(|index| self.eviction_score(*index));
+		}
+
+		let mut best: Option<(u32, usize)> = None;
+
+		for _ in 0..self.samples {
+			let index = self.next_index();
+			let score = self.eviction_score(index);
+
+			if best.is_none_or(|(best_score, _)| score > best_score) {
+				best = Some((score, index));
+			}
+		}
+
+		best.map(|(_, index)| index)
+	}
+
+	/// Returns a score which is higher for objects which should be evicted
+	/// sooner.
+	fn eviction_score(&self, index: usize) -> u32 {
+		let metadata = self.objects[index].metadata;
+
+		match self.sampling {
+			// the age of the object is robust to the clock wrapping
+			Sampling::Lru => self.clock.wrapping_sub(metadata),
+			Sampling::Lfu => u32::MAX - metadata,
+			Sampling::Random => 0,
+		}
+	}
+
+	fn next_index(&mut self) -> usize {
+		// splitmix64
+		self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+		let mut value = self.rng;
+		value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		value ^= value >> 31;
+
+		(value % self.objects.len() as u64) as usize
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{PolicyStack, SampledStack, Sampling};
+
+		let mut stack = SampledStack::new(Sampling::Lru, 5);
+
+		for access in [0, 1, 2, 0, 3] {
+			stack.insert(access, 1, None);
+		}
+
+		assert_eq!(stack.evict_one(), Some(1));
+
+		stack.insert(2, 1, None);
+
+		for eviction in [0, 3, 2] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+
+	#[test]
+	fn it_evicts_the_lru_sampled_object() {
+		use crate::worker::policy::policy_stack::{PolicyStack, SampledStack, Sampling};
+
+		let mut stack = SampledStack::new(Sampling::Lru, 3);
+
+		for key in 0..50 {
+			stack.insert(key, 1, None);
+		}
+
+		for key in (0..50).step_by(3) {
+			stack.update(key);
+		}
+
+		for _ in 0..40 {
+			assert_evicts_best_candidate(&mut stack);
+		}
+	}
+
+	#[test]
+	fn it_evicts_the_lfu_sampled_object() {
+		use crate::worker::policy::policy_stack::{PolicyStack, SampledStack, Sampling};
+
+		let mut stack = SampledStack::new(Sampling::Lfu, 3);
+
+		for key in 0..50 {
+			stack.insert(key, 1, None);
+
+			for _ in 0..key % 7 {
+				stack.update(key);
+			}
+		}
+
+		for _ in 0..40 {
+			assert_evicts_best_candidate(&mut stack);
+		}
+	}
+
+	#[test]
+	fn it_randomly_evicts_every_present_object() {
+		use std::collections::HashSet;
+
+		use crate::worker::policy::policy_stack::{PolicyStack, SampledStack, Sampling};
+
+		let mut stack = SampledStack::new(Sampling::Random, 1);
+
+		for key in 0..10 {
+			stack.insert(key, 1, None);
+		}
+
+		stack.remove(5);
+
+		let mut evicted = HashSet::new();
+
+		for _ in 0..1000 {
+			let key = stack.evict_one().expect("Could not evict object");
+
+			assert!(key < 10 && key != 5);
+			assert!(!stack.contains(key));
+
+			evicted.insert(key);
+			stack.insert(key, 1, None);
+		}
+
+		assert_eq!(evicted, (0..10).filter(|key| *key != 5).collect());
+	}
+
+	/// Evicts an object from the supplied stack and asserts it is the best
+	/// eviction candidate among the objects the stack sampled.
+	fn assert_evicts_best_candidate(stack: &mut super::SampledStack) {
+		use crate::worker::policy::policy_stack::PolicyStack;
+
+		// the candidates are sampled from a copy of the stack's RNG so that
+		// the eviction samples the same objects
+		let rng = stack.rng;
+
+		let candidates = (0..stack.samples)
+			.map(|_| stack.next_index())
+			.collect::<Vec<_>>()
+			.into_iter()
+			.map(|index| (stack.objects[index].key, stack.eviction_score(index)))
+			.collect::<Vec<_>>();
+
+		stack.rng = rng;
+
+		let best_score = candidates
+			.iter()
+			.map(|(_, score)| *score)
+			.max()
+			.expect("Could not sample candidates");
+
+		let key = stack.evict_one().expect("Could not evict object");
+
+		assert!(candidates.contains(&(key, best_score)));
+		assert!(!stack.contains(key));
+	}
+}
@@ -15,7 +15,7 @@ use kwik::collections::HashList;
 use crate::{
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
+	object::{ExpireTime, ObjectSize},
 	policy::PaperPolicy,
 	worker::policy::policy_stack::PolicyStack,
 };
@@ -44,7 +44,7 @@ impl PolicyStack for SieveStack {
 		self.stack.contains(&key)
 	}
 
-	fn insert(&mut self, key: HashedKey, _: ObjectSize) {
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, _: ExpireTime) {
 		if self.stack.contains(&key) {
 			return self.update(key);
 		}
@@ -132,13 +132,13 @@ mod tests {
 		let mut stack = SieveStack::default();
 
 		for access in [0, 1, 0, 2] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		assert_eq!(stack.evict_one(), Some(1));
 
 		for access in [3, 0, 1, 3] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		for eviction in [2, 1, 3, 0] {
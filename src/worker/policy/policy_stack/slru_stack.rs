@@ -0,0 +1,239 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	borrow::Borrow,
+	hash::{Hash, Hasher},
+};
+
+use kwik::collections::HashList;
+
+use crate::{
+	CacheSize,
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+pub struct SlruStack {
+	protected_ratio: f64,
+
+	probation: Stack,
+	protected: Stack,
+}
+
+struct Stack {
+	stack: HashList<Object, NoHasher>,
+
+	used_size: CacheSize,
+	max_size:  CacheSize,
+}
+
+struct Object {
+	key:  HashedKey,
+	size: ObjectSize,
+}
+
+impl PolicyStack for SlruStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		let PaperPolicy::Slru(protected_ratio) = policy else {
+			return false;
+		};
+
+		self.protected_ratio == *protected_ratio
+	}
+
+	fn len(&self) -> usize {
+		self.probation.stack.len() + self.protected.stack.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.probation.stack.contains(&key) || self.protected.stack.contains(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, _: ExpireTime) {
+		if self.contains(key) {
+			self.probation.update(key, size);
+			self.protected.update(key, size);
+
+			return self.update(key);
+		}
+
+		self.probation.insert(Object::new(key, size));
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		if let Some(object) = self.probation.remove(key) {
+			self.protected.insert(object);
+			return self.demote_protected();
+		}
+
+		self.protected.stack.move_front(&key);
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		self.probation.remove(key);
+		self.protected.remove(key);
+	}
+
+	fn resize(&mut self, max_size: CacheSize) {
+		self.protected.max_size = get_protected_size(self.protected_ratio, max_size);
+		self.demote_protected();
+	}
+
+	fn clear(&mut self) {
+		self.probation.clear();
+		self.protected.clear();
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		if let Some(object) = self.probation.pop() {
+			return Some(object.key);
+		}
+
+		self.protected.pop().map(|object| object.key)
+	}
+}
+
+impl SlruStack {
+	pub fn new(protected_ratio: f64, max_size: CacheSize) -> Self {
+		SlruStack {
+			protected_ratio,
+
+			probation: Stack::new(max_size),
+			protected: Stack::new(get_protected_size(protected_ratio, max_size)),
+		}
+	}
+
+	/// Moves the least recently used protected objects to the head of the
+	/// probationary segment until the protected segment fits in its share
+	/// of the cache.
+	fn demote_protected(&mut self) {
+		while self.protected.is_over_capacity() {
+			let Some(object) = self.protected.pop() else {
+				return;
+			};
+
+			self.probation.insert(object);
+		}
+	}
+}
+
+impl Stack {
+	fn new(max_size: CacheSize) -> Self {
+		Stack {
+			stack: HashList::with_hasher(NoHasher::default()),
+
+			used_size: 0,
+			max_size,
+		}
+	}
+
+	fn is_over_capacity(&self) -> bool {
+		self.used_size > self.max_size
+	}
+
+	fn insert(&mut self, object: Object) {
+		self.used_size += object.size as CacheSize;
+		self.stack.push_front(object);
+	}
+
+	fn update(&mut self, key: HashedKey, size: ObjectSize) {
+		let Some(object) = self.stack.get(&key) else {
+			return;
+		};
+
+		self.used_size -= object.size as CacheSize;
+		self.used_size += size as CacheSize;
+
+		self.stack
+			.update(&key, |object| object.size = size);
+	}
+
+	fn remove(&mut self, key: HashedKey) -> Option<Object> {
+		let object = self.stack.remove(&key)?;
+		self.used_size -= object.size as CacheSize;
+
+		Some(object)
+	}
+
+	fn pop(&mut self) -> Option<Object> {
+		let object = self.stack.pop_back()?;
+		self.used_size -= object.size as CacheSize;
+
+		Some(object)
+	}
+
+	fn clear(&mut self) {
+		self.stack.clear();
+		self.used_size = 0;
+	}
+}
+
+impl Object {
+	fn new(key: HashedKey, size: ObjectSize) -> Self {
+		Object {
+			key,
+			size,
+		}
+	}
+}
+
+impl Borrow<HashedKey> for Object {
+	fn borrow(&self) -> &HashedKey {
+		&self.key
+	}
+}
+
+impl Hash for Object {
+	fn hash<H>(&self, state: &mut H)
+	where
+		H: Hasher,
+	{
+		self.key.hash(state)
+	}
+}
+
+impl PartialEq for Object {
+	fn eq(&self, other: &Self) -> bool {
+		self.key == other.key
+	}
+}
+
+impl Eq for Object {}
+
+fn get_protected_size(protected_ratio: f64, max_size: CacheSize) -> CacheSize {
+	(protected_ratio * max_size as f64) as u64
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{PolicyStack, SlruStack};
+
+		let mut stack = SlruStack::new(0.5, 4);
+
+		for access in [0, 1, 2, 0, 1, 2, 3] {
+			stack.insert(access, 1, None);
+		}
+
+		// promoting 2 demoted 0 to the head of the probationary segment
+		assert_eq!(stack.evict_one(), Some(0));
+
+		// promoting 3 demotes 1
+		stack.insert(3, 1, None);
+
+		for eviction in [1, 2, 3] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+}
@@ -16,7 +16,7 @@ use crate::{
 	CacheSize,
 	HashedKey,
 	NoHasher,
-	object::ObjectSize,
+	object::{ExpireTime, ObjectSize},
 	policy::PaperPolicy,
 	worker::policy::policy_stack::PolicyStack,
 };
@@ -59,7 +59,7 @@ impl PolicyStack for TwoQStack {
 		self.any_stack_contains(key)
 	}
 
-	fn insert(&mut self, key: HashedKey, size: ObjectSize) {
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, _: ExpireTime) {
 		if self.any_stack_contains(key) {
 			self.a1_in.update(key, size);
 			self.a1_out.update(key, size);
@@ -243,7 +243,7 @@ mod tests {
 		for access in [
 			0, 1, 0, 2, 1, 3, 0, 4, 2, 5, 0,
 		] {
-			stack.insert(access, 1);
+			stack.insert(access, 1, None);
 		}
 
 		for eviction in [3, 4, 5, 1, 2, 0] {
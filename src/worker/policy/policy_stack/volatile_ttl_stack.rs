@@ -0,0 +1,141 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	collections::{BTreeSet, HashMap},
+	time::Instant,
+};
+
+use kwik::collections::HashList;
+
+use crate::{
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+#[derive(Default)]
+pub struct VolatileTtlStack {
+	// the objects with an expiry, which are evicted in the order they expire
+	expiries: BTreeSet<(Instant, HashedKey)>,
+	volatile: HashMap<HashedKey, Instant, NoHasher>,
+
+	// the objects without an expiry, which are evicted in LRU order once
+	// there are no objects with an expiry
+	persistent: HashList<HashedKey, NoHasher>,
+}
+
+impl PolicyStack for VolatileTtlStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		matches!(policy, PaperPolicy::VolatileTtl)
+	}
+
+	fn len(&self) -> usize {
+		self.volatile.len() + self.persistent.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.volatile.contains_key(&key) || self.persistent.contains(&key)
+	}
+
+	fn insert(&mut self, key: HashedKey, _: ObjectSize, expiry: ExpireTime) {
+		if self.contains(key) {
+			self.update_expiry(key, expiry);
+			return self.update(key);
+		}
+
+		match expiry {
+			Some(expiry) => {
+				self.expiries.insert((expiry, key));
+				self.volatile.insert(key, expiry);
+			},
+
+			None => {
+				self.persistent.push_front(key);
+			},
+		}
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		self.persistent.move_front(&key);
+	}
+
+	fn update_expiry(&mut self, key: HashedKey, expiry: ExpireTime) {
+		if expiry.is_none() && self.persistent.contains(&key) {
+			return;
+		}
+
+		if !self.contains(key) {
+			return;
+		}
+
+		self.remove(key);
+		self.insert(key, 0, expiry);
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		if let Some(expiry) = self.volatile.remove(&key) {
+			self.expiries.remove(&(expiry, key));
+		}
+
+		self.persistent.remove(&key);
+	}
+
+	fn clear(&mut self) {
+		self.expiries.clear();
+		self.volatile.clear();
+
+		self.persistent.clear();
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		if let Some((_, key)) = self.expiries.pop_first() {
+			self.volatile.remove(&key);
+			return Some(key);
+		}
+
+		self.persistent.pop_back()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use std::time::{Duration, Instant};
+
+		use crate::worker::policy::policy_stack::{PolicyStack, VolatileTtlStack};
+
+		let mut stack = VolatileTtlStack::default();
+		let now = Instant::now();
+
+		let accesses = [
+			(0, None),
+			(1, Some(now + Duration::from_secs(10))),
+			(2, None),
+			(3, Some(now + Duration::from_secs(5))),
+		];
+
+		for (access, expiry) in accesses {
+			stack.insert(access, 1, expiry);
+		}
+
+		assert_eq!(stack.evict_one(), Some(3));
+
+		// 2 now expires, so it is evicted before the objects without an expiry
+		stack.update_expiry(2, Some(now + Duration::from_secs(20)));
+		stack.insert(4, 1, None);
+
+		for eviction in [1, 2, 0, 4] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+}
@@ -0,0 +1,341 @@
+/*
+ * Copyright (c) Kia Shakiba
+ *
+ * This source code is licensed under the GNU AGPLv3 license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::{
+	borrow::Borrow,
+	hash::{Hash, Hasher},
+};
+
+use kwik::collections::HashList;
+
+use crate::{
+	CacheSize,
+	HashedKey,
+	NoHasher,
+	object::{ExpireTime, ObjectSize},
+	policy::PaperPolicy,
+	sketch::FrequencySketch,
+	worker::policy::policy_stack::PolicyStack,
+};
+
+// the ratio of the main region which is reserved for protected objects
+const PROTECTED_RATIO: f64 = 0.8;
+
+// the sketch is sized by the number of objects, which the stack estimates
+// from the cache size assuming an average object size
+const SKETCH_OBJECT_SIZE: CacheSize = 128;
+const MAX_SKETCH_CAPACITY: usize = 1 << 20;
+
+pub struct WTinyLfuStack {
+	window_ratio: f64,
+	main_size:    CacheSize,
+
+	window:    Stack,
+	probation: Stack,
+	protected: Stack,
+
+	sketch: FrequencySketch,
+}
+
+struct Stack {
+	stack: HashList<Object, NoHasher>,
+
+	used_size: CacheSize,
+	max_size:  CacheSize,
+}
+
+struct Object {
+	key:  HashedKey,
+	size: ObjectSize,
+}
+
+impl PolicyStack for WTinyLfuStack {
+	fn is_policy(&self, policy: &PaperPolicy) -> bool {
+		let PaperPolicy::WTinyLfu(window_ratio) = policy else {
+			return false;
+		};
+
+		self.window_ratio == *window_ratio
+	}
+
+	fn len(&self) -> usize {
+		self.window.stack.len() + self.probation.stack.len() + self.protected.stack.len()
+	}
+
+	fn contains(&self, key: HashedKey) -> bool {
+		self.any_stack_contains(key)
+	}
+
+	fn insert(&mut self, key: HashedKey, size: ObjectSize, _: ExpireTime) {
+		if self.any_stack_contains(key) {
+			self.window.update(key, size);
+			self.probation.update(key, size);
+			self.protected.update(key, size);
+
+			return self.update(key);
+		}
+
+		self.sketch.increment(key);
+		self.window.insert(Object::new(key, size));
+	}
+
+	fn update(&mut self, key: HashedKey) {
+		self.sketch.increment(key);
+
+		if self.window.stack.contains(&key) {
+			return self.window.stack.move_front(&key);
+		}
+
+		if let Some(object) = self.probation.remove(key) {
+			self.protected.insert(object);
+			return self.demote_protected();
+		}
+
+		self.protected.stack.move_front(&key);
+	}
+
+	fn remove(&mut self, key: HashedKey) {
+		self.window.remove(key);
+		self.probation.remove(key);
+		self.protected.remove(key);
+	}
+
+	fn resize(&mut self, max_size: CacheSize) {
+		let (window_size, main_size, protected_size) = get_region_sizes(self.window_ratio, max_size);
+
+		self.main_size = main_size;
+		self.window.max_size = window_size;
+		self.probation.max_size = main_size;
+		self.protected.max_size = protected_size;
+
+		self.demote_protected();
+	}
+
+	fn clear(&mut self) {
+		self.window.clear();
+		self.probation.clear();
+		self.protected.clear();
+		self.sketch.clear();
+	}
+
+	fn evict_one(&mut self) -> Option<HashedKey> {
+		self.drain_window();
+
+		if self.window.is_over_capacity() {
+			// the main region is full, so the window's victim must compete
+			// with the main region's victim to stay in the cache
+			let candidate = self.window.pop()?;
+
+			let Some(victim_key) = self.main_victim_key() else {
+				return Some(candidate.key);
+			};
+
+			if self.sketch.frequency(candidate.key) <= self.sketch.frequency(victim_key) {
+				return Some(candidate.key);
+			}
+
+			self.probation.remove(victim_key);
+			self.protected.remove(victim_key);
+			self.probation.insert(candidate);
+
+			return Some(victim_key);
+		}
+
+		if let Some(object) = self.probation.pop() {
+			return Some(object.key);
+		}
+
+		if let Some(object) = self.protected.pop() {
+			return Some(object.key);
+		}
+
+		self.window.pop().map(|object| object.key)
+	}
+}
+
+impl WTinyLfuStack {
+	pub fn new(window_ratio: f64, max_size: CacheSize) -> Self {
+		let (window_size, main_size, protected_size) = get_region_sizes(window_ratio, max_size);
+
+		let sketch_capacity = (max_size / SKETCH_OBJECT_SIZE) as usize;
+
+		WTinyLfuStack {
+			window_ratio,
+			main_size,
+
+			window: Stack::new(window_size),
+			probation: Stack::new(main_size),
+			protected: Stack::new(protected_size),
+
+			sketch: FrequencySketch::new(sketch_capacity.min(MAX_SKETCH_CAPACITY)),
+		}
+	}
+
+	fn any_stack_contains(&self, key: HashedKey) -> bool {
+		self.window.stack.contains(&key)
+			|| self.probation.stack.contains(&key)
+			|| self.protected.stack.contains(&key)
+	}
+
+	/// Moves objects from the window to the main region's probation segment
+	/// while the window is over capacity and the main region has space.
+	fn drain_window(&mut self) {
+		while self.window.is_over_capacity() {
+			let Some(object) = self.window.stack.back() else {
+				return;
+			};
+
+			let main_used_size = self.probation.used_size + self.protected.used_size;
+
+			if main_used_size + object.size as CacheSize > self.main_size {
+				return;
+			}
+
+			if let Some(object) = self.window.pop() {
+				self.probation.insert(object);
+			}
+		}
+	}
+
+	fn demote_protected(&mut self) {
+		while self.protected.is_over_capacity() {
+			let Some(object) = self.protected.pop() else {
+				return;
+			};
+
+			self.probation.insert(object);
+		}
+	}
+
+	fn main_victim_key(&self) -> Option<HashedKey> {
+		self.probation
+			.stack
+			.back()
+			.or_else(|| self.protected.stack.back())
+			.map(|object| object.key)
+	}
+}
+
+impl Stack {
+	fn new(max_size: CacheSize) -> Self {
+		Stack {
+			stack: HashList::with_hasher(NoHasher::default()),
+
+			used_size: 0,
+			max_size,
+		}
+	}
+
+	fn is_over_capacity(&self) -> bool {
+		self.used_size > self.max_size
+	}
+
+	fn insert(&mut self, object: Object) {
+		self.used_size += object.size as CacheSize;
+		self.stack.push_front(object);
+	}
+
+	fn update(&mut self, key: HashedKey, size: ObjectSize) {
+		let Some(object) = self.stack.get(&key) else {
+			return;
+		};
+
+		self.used_size -= object.size as CacheSize;
+		self.used_size += size as CacheSize;
+
+		self.stack
+			.update(&key, |object| object.size = size);
+	}
+
+	fn remove(&mut self, key: HashedKey) -> Option<Object> {
+		let object = self.stack.remove(&key)?;
+		self.used_size -= object.size as CacheSize;
+
+		Some(object)
+	}
+
+	fn pop(&mut self) -> Option<Object> {
+		let object = self.stack.pop_back()?;
+		self.used_size -= object.size as CacheSize;
+
+		Some(object)
+	}
+
+	fn clear(&mut self) {
+		self.stack.clear();
+		self.used_size = 0;
+	}
+}
+
+impl Object {
+	fn new(key: HashedKey, size: ObjectSize) -> Self {
+		Object {
+			key,
+			size,
+		}
+	}
+}
+
+impl Borrow<HashedKey> for Object {
+	fn borrow(&self) -> &HashedKey {
+		&self.key
+	}
+}
+
+impl Hash for Object {
+	fn hash<H>(&self, state: &mut H)
+	where
+		H: Hasher,
+	{
+		self.key.hash(state)
+	}
+}
+
+impl PartialEq for Object {
+	fn eq(&self, other: &Self) -> bool {
+		self.key == other.key
+	}
+}
+
+impl Eq for Object {}
+
+/// Returns the maximum sizes of the window, the main region, and the main
+/// region's protected segment.
+fn get_region_sizes(window_ratio: f64, max_size: CacheSize) -> (CacheSize, CacheSize, CacheSize) {
+	let window_size = (window_ratio * max_size as f64) as u64;
+	let main_size = max_size - window_size;
+	let protected_size = (PROTECTED_RATIO * main_size as f64) as u64;
+
+	(window_size, main_size, protected_size)
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn eviction_order_is_correct() {
+		use crate::worker::policy::policy_stack::{PolicyStack, WTinyLfuStack};
+
+		let mut stack = WTinyLfuStack::new(0.25, 4);
+
+		for access in [0, 0, 1, 2, 3, 0, 4] {
+			stack.insert(access, 1, None);
+		}
+
+		// 0 is more frequent than the main region's victim, so it is
+		// admitted to the main region
+		assert_eq!(stack.evict_one(), Some(1));
+
+		// accessing 2 in the probation segment protects it
+		stack.insert(2, 1, None);
+
+		for eviction in [3, 0, 2, 4] {
+			assert_eq!(stack.evict_one(), Some(eviction));
+		}
+
+		assert_eq!(stack.evict_one(), None);
+	}
+}
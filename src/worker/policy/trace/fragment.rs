@@ -18,9 +18,9 @@ use kwik::file::{
 use parking_lot::{Mutex, MutexGuard};
 use tempfile::tempfile;
 
-use crate::worker::policy::event::TraceEvent;
+use crate::worker::policy::event::TraceRecord;
 
-pub type Modifiers = (BinaryReader<TraceEvent>, BinaryWriter<TraceEvent>);
+pub type Modifiers = (BinaryReader<TraceRecord>, BinaryWriter<TraceRecord>);
 
 // REFRESH_AGE must be less than MAX_AGE
 const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
@@ -36,8 +36,8 @@ impl TraceFragment {
 		let reader_file = tempfile()?;
 		let writer_file = reader_file.try_clone()?;
 
-		let reader = BinaryReader::<TraceEvent>::from_file(reader_file)?;
-		let writer = BinaryWriter::<TraceEvent>::from_file(writer_file)?;
+		let reader = BinaryReader::<TraceRecord>::from_file(reader_file)?;
+		let writer = BinaryWriter::<TraceRecord>::from_file(writer_file)?;
 
 		let fragment = TraceFragment {
 			created:   Instant::now(),
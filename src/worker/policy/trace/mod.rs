@@ -7,7 +7,7 @@
 
 mod fragment;
 
-use std::{collections::VecDeque, sync::Arc, thread, time::Duration};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 
 use crossbeam_channel::Receiver;
 use kwik::file::FileWriter;
@@ -18,8 +18,10 @@ pub use crate::worker::policy::trace::fragment::TraceFragment;
 use crate::{
 	error::CacheError,
 	worker::{
+		ShutdownReceiver,
 		Worker,
 		policy::event::{StackEvent, TraceEvent},
+		sleep_or_shutdown,
 	},
 };
 
@@ -31,7 +33,9 @@ pub struct TraceWorker {
 }
 
 impl Worker for TraceWorker {
-	fn run(&mut self) -> Result<(), CacheError> {
+	fn run(&mut self, shutdown: &ShutdownReceiver) -> Result<(), CacheError> {
+		let mut is_shutdown = false;
+
 		loop {
 			let events = self.listener.try_iter().collect::<Vec<_>>();
 
@@ -57,9 +61,11 @@ impl Worker for TraceWorker {
 						let mut modifiers = fragment.lock();
 						let writer = &mut modifiers.1;
 
-						if let Err(err) = writer.write_chunk(&event) {
-							error!("Could not write to trace fragment: {err:?}");
-							return Err(CacheError::Internal);
+						for record in event.records() {
+							if let Err(err) = writer.write_chunk(&record) {
+								error!("Could not write to trace fragment: {err:?}");
+								return Err(CacheError::Internal);
+							}
 						}
 
 						should_flush = true;
@@ -84,7 +90,11 @@ impl Worker for TraceWorker {
 				}
 			}
 
-			thread::sleep(POLL_DELAY);
+			if is_shutdown {
+				return Ok(());
+			}
+
+			is_shutdown = sleep_or_shutdown(shutdown, POLL_DELAY);
 		}
 	}
 }
@@ -7,10 +7,7 @@
 
 mod expiries;
 
-use std::{
-	thread,
-	time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 use typesize::TypeSize;
 
@@ -18,10 +15,19 @@ use crate::{
 	EraseKey,
 	ObjectMapRef,
 	OverheadManagerRef,
+	RemovalCause,
+	RemovalListenerRef,
 	StatusRef,
 	erase,
 	error::CacheError,
-	worker::{Worker, WorkerEvent, WorkerReceiver, ttl::expiries::Expiries},
+	worker::{
+		ShutdownReceiver,
+		Worker,
+		WorkerEvent,
+		WorkerReceiver,
+		ttl::expiries::Expiries,
+		wait_for_events,
+	},
 };
 
 pub struct TtlWorker<K, V> {
@@ -30,6 +36,7 @@ pub struct TtlWorker<K, V> {
 	objects:          ObjectMapRef<K, V>,
 	status:           StatusRef,
 	overhead_manager: OverheadManagerRef,
+	removal_listener: RemovalListenerRef<K, V>,
 
 	expiries: Expiries,
 }
@@ -40,13 +47,21 @@ where
 	K: Eq + TypeSize,
 	V: TypeSize,
 {
-	fn run(&mut self) -> Result<(), CacheError> {
+	fn run(&mut self, shutdown: &ShutdownReceiver) -> Result<(), CacheError> {
+		let mut is_shutdown = false;
+
 		loop {
 			let now = Instant::now();
 
+			let mut events = Vec::<WorkerEvent>::new();
+
 			for event in self.listener.try_iter() {
+				event.flatten_into(&mut events);
+			}
+
+			for event in events {
 				match event {
-					WorkerEvent::Set(key, _, expiry, old_info) => {
+					WorkerEvent::Set(key, _, expiry, _, old_info) => {
 						if let Some((_, old_expiry)) = old_info {
 							self.expiries.remove(key, old_expiry);
 						}
@@ -72,17 +87,24 @@ where
 					&self.objects,
 					&self.status,
 					&self.overhead_manager,
+					&self.removal_listener,
 					Some(EraseKey::Hashed(key)),
+					RemovalCause::Expired,
 				)
 				.ok();
 			}
 
+			if is_shutdown {
+				return Ok(());
+			}
+
 			let delay_ms = match self.expiries.has_within(2) {
 				true => 1,
 				false => 1000,
 			};
 
-			thread::sleep(Duration::from_millis(delay_ms));
+			is_shutdown =
+				wait_for_events(&self.listener, shutdown, Duration::from_millis(delay_ms));
 		}
 	}
 }
@@ -93,6 +115,7 @@ impl<K, V> TtlWorker<K, V> {
 		objects: ObjectMapRef<K, V>,
 		status: StatusRef,
 		overhead_manager: OverheadManagerRef,
+		removal_listener: RemovalListenerRef<K, V>,
 	) -> Self {
 		TtlWorker {
 			listener,
@@ -100,6 +123,7 @@ impl<K, V> TtlWorker<K, V> {
 			objects,
 			status,
 			overhead_manager,
+			removal_listener,
 
 			expiries: Expiries::default(),
 		}